@@ -0,0 +1,29 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::{closed_set, specialized_dispatch};
+
+// Sealed trait implemented only by these types.
+closed_set!(pub trait Kind for u8, u16, f32);
+
+// The bound rejects other types where the function is called.
+fn describe<E: Kind>(expr: E) -> String {
+    specialized_dispatch!(
+        // The function may only be called with the types of `Kind`.
+        #[closed(Kind)]
+        E -> String,
+        // There is no default arm, but each type of `Kind` needs an arm.
+        fn (v: u8) => format!("u8: {}", v),
+        fn (v: u16) => format!("u16: {}", v),
+        fn (v: f32) => format!("f32: {:.1}", v),
+        expr,
+    )
+}
+
+fn main() {
+    assert_eq!(describe(5u8), "u8: 5");
+    assert_eq!(describe(10u16), "u16: 10");
+    assert_eq!(describe(1.5f32), "f32: 1.5");
+    // Fails to compile, since `f64` doesn't implement `Kind`.
+    // describe(1.5f64);
+    println!("Done!");
+}
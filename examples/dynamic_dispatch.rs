@@ -0,0 +1,21 @@
+use std::any::Any;
+
+use specialized_dispatch::dynamic_dispatch;
+
+fn describe(value: &dyn Any) -> String {
+    dynamic_dispatch!(
+        &dyn Any -> String,
+        fn (v: &u8) => format!("u8: {}", v),
+        fn (v: &String) => format!("String: {}", v),
+        default fn <T>(_: T) => format!("unknown type"),
+        value,
+    )
+}
+
+fn main() {
+    let values: Vec<Box<dyn Any>> =
+        vec![Box::new(5u8), Box::new(String::from("hi")), Box::new(1.5)];
+    let descriptions: Vec<_> = values.iter().map(|v| describe(v.as_ref())).collect();
+    assert_eq!(descriptions, ["u8: 5", "String: hi", "unknown type"]);
+    println!("Done!");
+}
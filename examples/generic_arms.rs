@@ -0,0 +1,27 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn describe<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T) => format!("something else"),
+        // Any byte array, which is specialized further below.
+        default fn <const N: usize>(v: [u8; N]) => format!("{} bytes", v.len()),
+        fn (v: [u8; 4]) => format!("four bytes: {:?}", v),
+        fn (v: Vec<u8>) => format!("byte vector of length {}", v.len()),
+        fn (v: Box<str>) => format!("boxed str: {}", v),
+        fn <T>(v: &[T]) => format!("slice of length {}", v.len()),
+        expr,
+    )
+}
+
+fn main() {
+    assert_eq!(describe([1u8, 2, 3]), "3 bytes");
+    assert_eq!(describe([1u8, 2, 3, 4]), "four bytes: [1, 2, 3, 4]");
+    assert_eq!(describe(vec![1u8, 2]), "byte vector of length 2");
+    assert_eq!(describe(Box::<str>::from("hi")), "boxed str: hi");
+    assert_eq!(describe(&[1u16, 2, 3][..]), "slice of length 3");
+    assert_eq!(describe(1.5), "something else");
+    println!("Done!");
+}
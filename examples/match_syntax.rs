@@ -0,0 +1,23 @@
+#![feature(min_specialization)]
+
+use std::fmt::Display;
+
+use specialized_dispatch::specialized_match;
+
+fn example<E: Display>(expr: E) -> String {
+    specialized_match!(expr: E -> String {
+        // Specialization for concrete type u8.
+        v: u8 => format!("u8: {}", v),
+        // Specialization for concrete type u16.
+        v: u16 => format!("u16: {}", v),
+        // Default implementation with a trait bound.
+        default <T: Display> v: T => format!("default value: {}", v),
+    })
+}
+
+fn main() {
+    assert_eq!(example(1.5), "default value: 1.5");
+    assert_eq!(example(5u8), "u8: 5");
+    assert_eq!(example(10u16), "u16: 10");
+    println!("Done!");
+}
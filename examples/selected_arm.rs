@@ -0,0 +1,24 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> (String, &'static str) {
+    specialized_dispatch!(
+        // Opt in to reporting the selected arm.
+        #[selected_arm]
+        E -> String,
+        default fn <T>(_: T) => format!("default value"),
+        // Arms are reported by their types, unless they are named.
+        fn (v: u8) => format!("u8: {}", v),
+        fn fast_path(v: u16) => format!("u16: {}", v),
+        expr,
+    )
+}
+
+fn main() {
+    assert_eq!(example(5u8), ("u8: 5".to_string(), "u8"));
+    assert_eq!(example(10u16), ("u16: 10".to_string(), "fast_path"));
+    // Note that `&u8` is not `u8`, so the default arm is selected.
+    assert_eq!(example(&5u8), ("default value".to_string(), "T"));
+    println!("Done!");
+}
@@ -12,7 +12,7 @@ fn example<Arg: Display>(arg: Arg) -> String {
         default fn <T: Display>(v: T) => {
             format!("default value: {}", v)
         },
-        // Note that specializations also need to satisfy the same bound.
+        // Specializations not satisfying the bound are never selected, so they are warned.
         fn (v: u8) => format!("u8: {}", v),
         fn (v: u16) => format!("u16: {}", v),
         arg,
@@ -0,0 +1,21 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn type_name<T>() -> &'static str {
+    specialized_dispatch!(
+        T -> &'static str,
+        // Specializations for concrete types, given in angle brackets.
+        fn <u8> => "unsigned byte",
+        fn <u16> => "unsigned short",
+        // Default implementation.
+        default fn <X> => "something else",
+    )
+}
+
+fn main() {
+    assert_eq!(type_name::<u8>(), "unsigned byte");
+    assert_eq!(type_name::<u16>(), "unsigned short");
+    assert_eq!(type_name::<f32>(), "something else");
+    println!("Done!");
+}
@@ -0,0 +1,108 @@
+//! Dispatches side by side with their hand-written equivalents. Each pair compiles to the same
+//! machine code in release builds, which is checked by `tests/zero_cost.rs`. Functions aren't
+//! mangled or inlined, so that they can be found in the assembly with
+//! `cargo rustc --release --example zero_cost -- --emit=asm`.
+#![feature(min_specialization)]
+
+use std::any::Any;
+
+use specialized_dispatch::{dynamic_dispatch, specialized_dispatch};
+
+fn scale<E>(value: E) -> u32 {
+    specialized_dispatch!(
+        E -> u32,
+        default fn <T>(_: T) => 0,
+        fn (v: u8) => u32::from(v) * 3,
+        fn (v: u16) => u32::from(v) + 7,
+        value,
+    )
+}
+
+fn sum<E>(values: &[E]) -> u32 {
+    specialized_dispatch!(
+        #[bulk]
+        &[E] -> u32,
+        default fn <T>(v: &[T]) => v.len() as u32,
+        fn (v: &[u8]) => v.iter().map(|x| u32::from(*x)).sum(),
+        values,
+    )
+}
+
+fn scale_dyn(value: &dyn Any) -> u32 {
+    dynamic_dispatch!(
+        &dyn Any -> u32,
+        fn (v: &u8) => u32::from(*v) * 3,
+        fn (v: &u16) => u32::from(*v) + 7,
+        default fn <T>(_: T) => 0,
+        value,
+    )
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn dispatch_u8(value: u8) -> u32 {
+    scale(value)
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn manual_u8(value: u8) -> u32 {
+    u32::from(value) * 3
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn dispatch_u16(value: u16) -> u32 {
+    scale(value)
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn manual_u16(value: u16) -> u32 {
+    u32::from(value) + 7
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn dispatch_default(value: u64) -> u32 {
+    scale(value)
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn manual_default(_: u64) -> u32 {
+    0
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn dispatch_bulk(values: &[u8]) -> u32 {
+    sum(values)
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn manual_bulk(values: &[u8]) -> u32 {
+    values.iter().map(|x| u32::from(*x)).sum()
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn dynamic_u16(value: u16) -> u32 {
+    scale_dyn(&value)
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn manual_dynamic_u16(value: u16) -> u32 {
+    u32::from(value) + 7
+}
+
+fn main() {
+    assert_eq!(dispatch_u8(5), manual_u8(5));
+    assert_eq!(dispatch_u16(5), manual_u16(5));
+    assert_eq!(dispatch_default(5), manual_default(5));
+    assert_eq!(dispatch_bulk(&[1, 2, 3]), manual_bulk(&[1, 2, 3]));
+    assert_eq!(dynamic_u16(5), manual_dynamic_u16(5));
+    println!("Done!");
+}
@@ -0,0 +1,23 @@
+//! Detects whether the compiler accepts unstable features, so that the macros can explain which
+//! toolchain they require instead of failing with errors in the generated code.
+
+use std::{env, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=RUSTC_BOOTSTRAP");
+    println!("cargo:rustc-check-cfg=cfg(stable_toolchain)");
+
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    // Assume unstable features are available if the version can't be determined, since rustc
+    // will complain about them anyway.
+    let unstable =
+        version.is_none_or(|version| version.contains("nightly") || version.contains("-dev"));
+    if !unstable && env::var_os("RUSTC_BOOTSTRAP").is_none() {
+        println!("cargo:rustc-cfg=stable_toolchain");
+    }
+}
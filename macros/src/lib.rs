@@ -0,0 +1,3942 @@
+//! Procedural macros of [`specialized-dispatch`] crate. This crate is an implementation detail,
+//! please depend on [`specialized-dispatch`] instead.
+//!
+//! [`specialized-dispatch`]: https://docs.rs/specialized-dispatch
+
+use proc_macro::TokenStream;
+use proc_macro2::{Delimiter, Span as Span2, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{
+    braced, parenthesized,
+    parse::{discouraged::Speculative, Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token,
+    visit::{self, Visit},
+    Attribute, Data, DeriveInput, Expr, GenericParam, Ident, ImplItem, Index, ItemImpl, Lifetime,
+    LifetimeParam, Member, Result, Token, TraitBoundModifier, Type, TypeParam, TypeParamBound,
+    Visibility, WhereClause,
+};
+
+/// Parses either an identifier or an underscore for arguments of specializations.
+// TODO(ozars): Make this accept patterns for unpacking arguments. Maybe switch to using
+// `syn::PatType`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum FnArgName {
+    Ident(Ident),
+    Underscore(Token![_]),
+}
+
+impl Parse for FnArgName {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Ident) {
+            Ok(Self::Ident(input.parse()?))
+        } else if input.peek(Token![_]) {
+            Ok(Self::Underscore(input.parse()?))
+        } else {
+            Err(input.error("expected identifier or underscore"))
+        }
+    }
+}
+
+impl ToTokens for FnArgName {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            Self::Ident(ident) => ident.to_tokens(tokens),
+            Self::Underscore(underscore) => underscore.to_tokens(tokens),
+        }
+    }
+}
+
+/// Function argument with name and type.
+#[derive(Debug, Eq, PartialEq, Clone)]
+struct FnArg {
+    r#mut: Option<Token![mut]>,
+    name: FnArgName,
+    ty: Type,
+}
+
+impl Parse for FnArg {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let r#mut = input.parse()?;
+        let name = input.parse()?;
+        let _ = input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        Ok(Self { r#mut, name, ty })
+    }
+}
+
+impl ToTokens for FnArg {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.name.to_tokens(tokens);
+        Token![:](Span2::mixed_site()).to_tokens(tokens);
+        self.ty.to_tokens(tokens);
+    }
+}
+
+/// Parses optional generic parameters enclosed in angle brackets, e.g. `<T: Display>`.
+fn parse_generic_params(input: ParseStream) -> Result<Option<Punctuated<GenericParam, Token![,]>>> {
+    if !input.peek(Token![<]) {
+        return Ok(None);
+    }
+    let _ = input.parse::<Token![<]>()?;
+    let generic_params = Punctuated::<GenericParam, Token![,]>::parse_separated_nonempty(input)?;
+    let _ = input.parse::<Token![>]>()?;
+    Ok(Some(generic_params))
+}
+
+/// Input of a specialization arm. It's either an argument receiving the dispatched value, or only a
+/// type when dispatching without a value.
+#[derive(Debug, Eq, PartialEq)]
+enum ArmInput {
+    Value(FnArg),
+    Type(Type),
+}
+
+impl ArmInput {
+    /// Returns the type this arm is specialized for.
+    fn ty(&self) -> &Type {
+        match self {
+            Self::Value(arg) => &arg.ty,
+            Self::Type(ty) => ty,
+        }
+    }
+}
+
+/// Represents an arm for specialized dispatch macro.
+#[derive(Debug, Eq, PartialEq)]
+struct DispatchArmExpr {
+    attrs: Vec<Attribute>,
+    default: Option<Token![default]>,
+    unsafety: Option<Token![unsafe]>,
+    label: Option<Ident>,
+    generic_params: Option<Punctuated<GenericParam, Token![,]>>,
+    input: ArmInput,
+    extra_args: Vec<FnArg>,
+    where_clause: Option<WhereClause>,
+    body: Expr,
+}
+
+/// Returns the only type parameter among given generic parameters, if there is exactly one.
+fn single_type_param(generic_params: &Punctuated<GenericParam, Token![,]>) -> Option<&Ident> {
+    let mut type_params = generic_params.iter().filter_map(|param| match param {
+        GenericParam::Type(type_param) => Some(&type_param.ident),
+        _ => None,
+    });
+    match (type_params.next(), type_params.next()) {
+        (Some(ident), None) => Some(ident),
+        _ => None,
+    }
+}
+
+impl Parse for DispatchArmExpr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let default = input.parse::<Option<Token![default]>>()?;
+        let unsafety = input.parse()?;
+        let _ = input.parse::<Token![fn]>()?;
+        let label = input.parse()?;
+        // Angle brackets right after `fn` are ambiguous: they contain generic parameters if they
+        // are followed by arguments or by the type of a type-only arm, or if this is the default
+        // arm. Otherwise, they contain the type of a type-only arm (e.g. `fn <u8> => ...`).
+        let generics_fork = input.fork();
+        let generic_params = match parse_generic_params(&generics_fork) {
+            Ok(Some(generic_params))
+                if default.is_some()
+                    || generics_fork.peek(token::Paren)
+                    || generics_fork.peek(Token![<]) =>
+            {
+                input.advance_to(&generics_fork);
+                Some(generic_params)
+            }
+            _ => None,
+        };
+        let (arm_input, extra_args) = if input.peek(token::Paren) {
+            let input_expr_content;
+            let _ = parenthesized!(input_expr_content in input);
+            let input_expr = input_expr_content.parse()?;
+            let extra_args = if input_expr_content.peek(Token![,]) {
+                let _ = input_expr_content.parse::<Token![,]>()?;
+                Punctuated::<FnArg, Token![,]>::parse_separated_nonempty(&input_expr_content)?
+                    .into_iter()
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            (ArmInput::Value(input_expr), extra_args)
+        } else if input.peek(Token![<]) {
+            let _ = input.parse::<Token![<]>()?;
+            let ty = input.parse()?;
+            let _ = input.parse::<Token![>]>()?;
+            (ArmInput::Type(ty), Vec::new())
+        } else if let Some(ident) = generic_params.as_ref().and_then(single_type_param) {
+            (ArmInput::Type(parse_quote!(#ident)), Vec::new())
+        } else {
+            return Err(
+                input.error("expected arguments in parentheses or a type in angle brackets")
+            );
+        };
+        let where_clause = input.parse()?;
+        let _ = input.parse::<Token![=>]>()?;
+        let body = input.parse()?;
+        Ok(Self {
+            attrs,
+            default,
+            unsafety,
+            label,
+            generic_params,
+            input: arm_input,
+            extra_args,
+            where_clause,
+            body,
+        })
+    }
+}
+
+/// This is entry point for handling arguments of `specialized_dispatch` macro. It parses arguments
+/// of the specialized dispatch macro and expands to the corresponding implementation.
+#[derive(Debug, Eq, PartialEq)]
+struct SpecializedDispatchExpr {
+    selected_arm: bool,
+    try_arms: bool,
+    control_flow: bool,
+    unsafety: Option<Token![unsafe]>,
+    generic_params: Option<Punctuated<GenericParam, Token![,]>>,
+    lifted: Option<Punctuated<GenericParam, Token![,]>>,
+    closed: Option<ClosedSet>,
+    bulk: Option<BulkSlice>,
+    from_type: Type,
+    to_type: Type,
+    arms: Vec<DispatchArmExpr>,
+    input_expr: Option<Expr>,
+    extra_args: Vec<Expr>,
+}
+
+/// Complete set of types a dispatch may be instantiated with, declared by `#[closed(Kind)]` option
+/// naming the trait declared by `closed_set` macro.
+#[derive(Debug, Eq, PartialEq)]
+struct ClosedSet {
+    keyword: Ident,
+    path: syn::Path,
+}
+
+/// Parses a non-empty list of distinct types, which are the types of a closed set declared by
+/// `closed_set` macro, whose name is given for errors.
+fn parse_closed_types(input: ParseStream, span: Span2, name: &str) -> Result<Vec<Type>> {
+    let types: Vec<Type> = Punctuated::<Type, Token![,]>::parse_terminated(input)?
+        .into_iter()
+        .collect();
+    if types.is_empty() {
+        return Err(syn::Error::new(
+            span,
+            format!("expected at least one type in `{}`", name),
+        ));
+    }
+    let mut seen = Vec::new();
+    for ty in &types {
+        let mut type_name = String::new();
+        write_type_tokens(ty.to_token_stream(), &mut type_name);
+        if seen.contains(&type_name) {
+            return Err(syn::Error::new_spanned(
+                ty,
+                format!("duplicate type `{}` in `{}`", type_name, name),
+            ));
+        }
+        seen.push(type_name);
+    }
+    Ok(types)
+}
+
+/// Slices dispatched on their element type by `#[bulk]` option. The dispatched type and types of
+/// arms are replaced with their element types while parsing, so that the helper trait is
+/// implemented for element types taking the whole slice.
+#[derive(Debug, Eq, PartialEq)]
+struct BulkSlice {
+    keyword: Ident,
+    container: BulkContainer,
+}
+
+/// Kinds of slices dispatched by `#[bulk]` option.
+#[derive(Debug, Eq, PartialEq)]
+enum BulkContainer {
+    /// `&[T]` or `&mut [T]`.
+    Slice(Option<Token![mut]>),
+    /// `Vec<T>` taken by value, with the path to `Vec` as written in the dispatched type.
+    Vec(syn::Path),
+}
+
+impl BulkSlice {
+    /// Returns the element type of a slice of the expected kind.
+    fn element_type(&self, ty: &Type) -> Result<Type> {
+        match (&self.container, ty) {
+            (BulkContainer::Slice(mutability), Type::Reference(reference)) => {
+                if let (Type::Slice(slice), true) = (
+                    &*reference.elem,
+                    reference.mutability.is_some() == mutability.is_some(),
+                ) {
+                    return Ok((*slice.elem).clone());
+                }
+            }
+            (BulkContainer::Vec(_), ty) => {
+                if let Some((_, elem)) = vec_element_type(ty) {
+                    return Ok(elem.clone());
+                }
+            }
+            _ => {}
+        }
+        let expected = match &self.container {
+            BulkContainer::Slice(Some(_)) => "a slice such as `&mut [T]`",
+            BulkContainer::Slice(None) => "a slice such as `&[T]`",
+            BulkContainer::Vec(_) => "a vector such as `Vec<T>`",
+        };
+        Err(syn::Error::new_spanned(
+            ty,
+            format!("expected {} for `bulk`", expected),
+        ))
+    }
+
+    /// Generates the slice of the given element type.
+    fn slice_of(&self, element_type: TokenStream2) -> TokenStream2 {
+        match &self.container {
+            BulkContainer::Slice(mutability) => quote! {&#mutability [#element_type]},
+            BulkContainer::Vec(path) => quote! {#path<#element_type>},
+        }
+    }
+}
+
+/// Returns the path to `Vec` without generic arguments and the element type of a `Vec<T>` type.
+fn vec_element_type(ty: &Type) -> Option<(syn::Path, &Type)> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match (
+        path.qself.is_none() && segment.ident == "Vec",
+        args.args.len(),
+        args.args.first(),
+    ) {
+        (true, 1, Some(syn::GenericArgument::Type(elem))) => {
+            let mut vec_path = path.path.clone();
+            if let Some(last) = vec_path.segments.last_mut() {
+                last.arguments = syn::PathArguments::None;
+            }
+            Some((vec_path, elem))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the slices dispatched by `#[bulk]` option given the slice or vector dispatched.
+fn parse_bulk_slice(keyword: Option<Ident>, from_type: &Type) -> Result<Option<BulkSlice>> {
+    let Some(keyword) = keyword else {
+        return Ok(None);
+    };
+    let container = match (from_type, vec_element_type(from_type)) {
+        (_, Some((path, _))) => BulkContainer::Vec(path),
+        (Type::Reference(reference), _) => BulkContainer::Slice(reference.mutability),
+        _ => BulkContainer::Slice(None),
+    };
+    let bulk = BulkSlice { keyword, container };
+    bulk.element_type(from_type)?;
+    Ok(Some(bulk))
+}
+
+/// Options given as outer attributes before the macro arguments.
+#[derive(Default)]
+struct DispatchOptions {
+    /// `#[selected_arm]`: Reports the selected arm along with the result.
+    selected_arm: bool,
+    /// `#[try_arms]`: Makes the arms like `try` blocks, i.e. their values are wrapped in `Ok`.
+    try_arms: bool,
+    /// `#[control_flow]`: Makes the arms return `ControlFlow`, breaking of which returns from the
+    /// enclosing function.
+    control_flow: bool,
+    /// `#[closed(Kind)]`: Restricts the dispatched type to the types of a closed set declared by
+    /// `closed_set` macro.
+    closed: Option<ClosedSet>,
+    /// `#[bulk]`: Dispatches a slice or a `Vec` on its element type, calling the arm with the whole
+    /// slice.
+    bulk: Option<Ident>,
+    /// `#[lift(S: Serializer, ...)]`: Generic parameters of the helper trait, e.g. of the enclosing
+    /// function, which can be used anywhere in the arms.
+    lifted: Option<Punctuated<GenericParam, Token![,]>>,
+}
+
+/// Parses options given as outer attributes before the macro arguments, e.g. `#[selected_arm]`.
+fn parse_dispatch_options(input: ParseStream) -> Result<DispatchOptions> {
+    let mut options = DispatchOptions::default();
+    for attr in input.call(Attribute::parse_outer)? {
+        if attr.path().is_ident("selected_arm") {
+            attr.meta.require_path_only()?;
+            options.selected_arm = true;
+        } else if attr.path().is_ident("try_arms") {
+            attr.meta.require_path_only()?;
+            options.try_arms = true;
+        } else if attr.path().is_ident("bulk") {
+            attr.meta.require_path_only()?;
+            options.bulk = attr.path().get_ident().cloned();
+        } else if attr.path().is_ident("control_flow") {
+            attr.meta.require_path_only()?;
+            options.control_flow = true;
+        } else if attr.path().is_ident("closed") {
+            let path = attr.parse_args()?;
+            let keyword = attr.path().get_ident().unwrap().clone();
+            options.closed = Some(ClosedSet { keyword, path });
+        } else if attr.path().is_ident("lift") {
+            let params =
+                attr.parse_args_with(Punctuated::<GenericParam, Token![,]>::parse_terminated)?;
+            if params.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected at least one generic parameter in `lift`",
+                ));
+            }
+            options.lifted = Some(params);
+        } else {
+            return Err(syn::Error::new_spanned(attr, "unknown dispatch option"));
+        }
+        if options.try_arms && options.control_flow {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`try_arms` and `control_flow` can't be used together",
+            ));
+        }
+    }
+    Ok(options)
+}
+
+/// Parses generic parameters of the dispatch given before the dispatched type, e.g. `<W: Write>` in
+/// `<W: Write> E -> R`. Angle brackets starting a qualified path, e.g. `<E as Trait>::Assoc`, are
+/// left to be parsed as the dispatched type.
+fn parse_dispatch_generic_params(
+    input: ParseStream,
+) -> Result<Option<Punctuated<GenericParam, Token![,]>>> {
+    let fork = input.fork();
+    match parse_generic_params(&fork) {
+        Ok(Some(generic_params)) if !fork.peek(Token![::]) => {
+            input.advance_to(&fork);
+            Ok(Some(generic_params))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses specialization arms as long as they start with an attribute, `default`, `unsafe` or `fn`.
+/// `unsafe` followed by braces starts an unsafe block as the expression being dispatched instead.
+fn parse_punctuated_arms(input: &ParseStream) -> Result<Punctuated<DispatchArmExpr, Token![,]>> {
+    let mut arms = Punctuated::new();
+    loop {
+        if input.peek(Token![#])
+            || input.peek(Token![default])
+            || (input.peek(Token![unsafe]) && !input.peek2(token::Brace))
+            || input.peek(Token![fn])
+        {
+            arms.push(input.parse()?);
+        } else {
+            break;
+        }
+        if input.peek(Token![,])
+            && (input.peek2(Token![#])
+                || input.peek2(Token![default])
+                || (input.peek2(Token![unsafe]) && !input.peek3(token::Brace))
+                || input.peek2(Token![fn]))
+        {
+            let _ = input.parse::<Token![,]>()?;
+        } else {
+            break;
+        }
+    }
+    Ok(arms)
+}
+
+impl Parse for SpecializedDispatchExpr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let expr = Self::parse_unchecked(input)?;
+        expr.validate()?;
+        expr.check_duplicate_arms()?;
+        Ok(expr)
+    }
+}
+
+impl SpecializedDispatchExpr {
+    /// Parses arguments of the macro without checking arms against each other.
+    fn parse_unchecked(input: ParseStream) -> Result<Self> {
+        let DispatchOptions {
+            selected_arm,
+            try_arms,
+            control_flow,
+            closed,
+            bulk,
+            lifted,
+        } = parse_dispatch_options(input)?;
+        // `unsafe` followed by `fn` or `extern` starts the type of an unsafe function pointer.
+        let unsafety = if input.peek(Token![unsafe])
+            && !input.peek2(Token![fn])
+            && !input.peek2(Token![extern])
+        {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let generic_params = parse_dispatch_generic_params(input)?;
+        let from_type = input.parse()?;
+        let bulk = parse_bulk_slice(bulk, &from_type)?;
+        let _ = input.parse::<Token![->]>()?;
+        let to_type = input.parse()?;
+        let _ = input.parse::<Token![,]>()?;
+        let arms: Vec<DispatchArmExpr> = parse_punctuated_arms(&input)?.into_iter().collect();
+        let type_only = arms
+            .first()
+            .is_some_and(|arm| matches!(arm.input, ArmInput::Type(_)));
+        for arm in &arms {
+            if matches!(arm.input, ArmInput::Type(_)) != type_only {
+                return Err(syn::Error::new_spanned(
+                    arm.input.ty(),
+                    "type-only arms cannot be mixed with arms taking arguments",
+                ));
+            }
+        }
+        let (input_expr, extra_args) = if type_only {
+            let _ = input.parse::<Option<Token![,]>>()?;
+            (None, Vec::new())
+        } else {
+            let _ = input.parse::<Token![,]>()?;
+            let input_expr = input.parse()?;
+            let _ = input.parse::<Token![,]>().ok();
+            let extra_args = Punctuated::<Expr, Token![,]>::parse_terminated(input)?
+                .into_iter()
+                .collect();
+            (Some(input_expr), extra_args)
+        };
+        Self {
+            selected_arm,
+            try_arms,
+            control_flow,
+            unsafety,
+            generic_params,
+            lifted,
+            closed,
+            bulk,
+            from_type,
+            to_type,
+            arms,
+            input_expr,
+            extra_args,
+        }
+        .into_element_types()
+    }
+
+    /// Replaces the dispatched type and types of arms with their element types for `#[bulk]`
+    /// option.
+    fn into_element_types(mut self) -> Result<Self> {
+        let Some(bulk) = &self.bulk else {
+            return Ok(self);
+        };
+        self.from_type = bulk.element_type(&self.from_type)?;
+        for arm in &mut self.arms {
+            match &mut arm.input {
+                ArmInput::Value(arg) => arg.ty = bulk.element_type(&arg.ty)?,
+                ArmInput::Type(ty) => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "type-only arms can't be used with `bulk`",
+                    ))
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Returns whether the type is of the given name regardless of its path, e.g. `Result` matches
+/// aliases of the same name such as `io::Result<T>`.
+fn is_type_named(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Path(path) if path.path.segments.last().is_some_and(|segment| segment.ident == name))
+}
+
+/// Returns the first `_` among the generic arguments of a type path, e.g. in `Result<u8, _>`.
+fn inferred_type_argument(ty: &Type) -> Option<&syn::TypeInfer> {
+    let Type::Path(path) = ty else { return None };
+    let syn::PathArguments::AngleBracketed(args) = &path.path.segments.last()?.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(Type::Infer(infer)) => Some(infer),
+        _ => None,
+    })
+}
+
+/// Formats the number of extra arguments for error messages.
+fn extra_args_count(count: usize) -> String {
+    match count {
+        1 => "1 extra argument".to_string(),
+        _ => format!("{} extra arguments", count),
+    }
+}
+
+impl SpecializedDispatchExpr {
+    /// Checks that arms are consistent with each other and with the dispatch call, so that errors
+    /// are reported here rather than as confusing errors about the generated trait.
+    fn validate(&self) -> Result<()> {
+        // The default arm is optional if the set of dispatched types is closed, since each of them
+        // is checked to have an arm when type checking instead. Then, the first arm sets the
+        // expected arguments.
+        let (first_arm, first_arm_kind) = match self.arms.iter().find(|arm| arm.default.is_some()) {
+            Some(arm) => (arm, "default"),
+            None => match (&self.closed, self.arms.first()) {
+                (Some(_), Some(arm)) => (arm, "first"),
+                _ => {
+                    return Err(syn::Error::new(
+                        Span2::call_site(),
+                        "expected a default arm",
+                    ))
+                }
+            },
+        };
+        if self.try_arms && !is_type_named(&self.to_type, "Result") {
+            return Err(syn::Error::new_spanned(
+                &self.to_type,
+                "expected a `Result` return type for `try_arms`",
+            ));
+        }
+        if self.try_arms {
+            if let Some(infer) = inferred_type_argument(&self.to_type) {
+                return Err(syn::Error::new_spanned(
+                    infer,
+                    "types of the `Result` can't be inferred for `try_arms`; declare them, and \
+                     arm errors are converted into them with `From`",
+                ));
+            }
+        }
+        if self.control_flow && !is_type_named(&self.to_type, "ControlFlow") {
+            return Err(syn::Error::new_spanned(
+                &self.to_type,
+                "expected a `ControlFlow` return type for `control_flow`",
+            ));
+        }
+
+        if self.unsafety.is_none() {
+            if let Some(unsafety) = self.arms.iter().find_map(|arm| arm.unsafety) {
+                return Err(syn::Error::new_spanned(
+                    unsafety,
+                    "`unsafe` arms are only allowed in an `unsafe` dispatch",
+                ));
+            }
+        }
+
+        let num_extra_args = first_arm.extra_args.len();
+        for arm in &self.arms {
+            if arm.extra_args.len() != num_extra_args {
+                let span = match arm.extra_args.get(num_extra_args) {
+                    Some(surplus) => surplus.to_token_stream(),
+                    None => arm.input.ty().to_token_stream(),
+                };
+                return Err(syn::Error::new_spanned(
+                    span,
+                    format!(
+                        "expected {} as in the {} arm, found {}",
+                        extra_args_count(num_extra_args),
+                        first_arm_kind,
+                        arm.extra_args.len(),
+                    ),
+                ));
+            }
+        }
+
+        if let Some(input_expr) = &self.input_expr {
+            if self.extra_args.len() != num_extra_args {
+                let span = match self.extra_args.get(num_extra_args) {
+                    Some(surplus) => surplus.to_token_stream(),
+                    None => input_expr.to_token_stream(),
+                };
+                return Err(syn::Error::new_spanned(
+                    span,
+                    format!(
+                        "expected {} to pass to the arms, found {}",
+                        extra_args_count(num_extra_args),
+                        self.extra_args.len(),
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no two arms are specialized for the same type, which would otherwise be
+    /// reported as conflicting implementations of the generated trait.
+    fn check_duplicate_arms(&self) -> Result<()> {
+        let mut seen = Vec::new();
+        for arm in &self.arms {
+            let mut ty = String::new();
+            write_type_tokens(arm.input.ty().to_token_stream(), &mut ty);
+            let key = (
+                arm.default.is_some(),
+                arm_cfg(arm),
+                arm.generic_params.to_token_stream().to_string(),
+                ty,
+            );
+            if seen.contains(&key) {
+                return Err(syn::Error::new_spanned(
+                    arm.input.ty(),
+                    format!("duplicate arm for `{}`", key.3),
+                ));
+            }
+            seen.push(key);
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if the expression needs to be followed by a comma when used as the body of a
+/// `specialized_match` arm, mirroring the rules of `match` arms.
+fn match_arm_requires_comma(body: &Expr) -> bool {
+    !matches!(
+        body,
+        Expr::Block(_)
+            | Expr::ForLoop(_)
+            | Expr::If(_)
+            | Expr::Loop(_)
+            | Expr::Match(_)
+            | Expr::TryBlock(_)
+            | Expr::Unsafe(_)
+            | Expr::While(_)
+    )
+}
+
+/// Parses a single arm of `specialized_match` macro into a dispatch arm.
+///
+/// Arms are of the form `[default] [unsafe] [<generic params>] [mut] name: Type [where clause] =>
+/// body`, optionally preceded by outer attributes. A catch-all arm, `_ => body` or `name => body`, is a
+/// shorthand for a default arm accepting any type.
+fn parse_match_arm(input: ParseStream, bulk: Option<&BulkSlice>) -> Result<DispatchArmExpr> {
+    let attrs = input.call(Attribute::parse_outer)?;
+    let mut default = input.parse::<Option<Token![default]>>()?;
+    let unsafety = input.parse()?;
+    let mut generic_params = parse_generic_params(input)?;
+    let r#mut = input.parse()?;
+    let name = input.parse()?;
+    let ty = if input.peek(Token![:]) {
+        let _ = input.parse::<Token![:]>()?;
+        input.parse()?
+    } else if generic_params.is_none() {
+        // Catch-all arm, which is the default implementation for any type.
+        let tpl = Ident::new("T", Span2::mixed_site());
+        default = Some(default.unwrap_or_default());
+        generic_params = Some(parse_quote!(#tpl));
+        match bulk {
+            Some(bulk) => syn::parse2(bulk.slice_of(quote! {#tpl}))?,
+            None => parse_quote!(#tpl),
+        }
+    } else {
+        return Err(input.error("expected `:` followed by type of the arm"));
+    };
+    let where_clause = input.parse()?;
+    let _ = input.parse::<Token![=>]>()?;
+    // Block-like bodies end the arm as in `match`, rather than continuing as e.g. a comparison
+    // with the next arm starting with generic parameters.
+    let body = Expr::parse_with_earlier_boundary_rule(input)?;
+    Ok(DispatchArmExpr {
+        attrs,
+        default,
+        unsafety,
+        label: None,
+        generic_params,
+        input: ArmInput::Value(FnArg { r#mut, name, ty }),
+        extra_args: Vec::new(),
+        where_clause,
+        body,
+    })
+}
+
+/// This is entry point for handling arguments of `specialized_match` macro. It parses the
+/// `match`-like syntax into the same representation used by `specialized_dispatch` macro.
+fn parse_specialized_match(input: ParseStream) -> Result<SpecializedDispatchExpr> {
+    let DispatchOptions {
+        selected_arm,
+        try_arms,
+        control_flow,
+        closed,
+        bulk,
+        lifted,
+    } = parse_dispatch_options(input)?;
+    // `unsafe` followed by braces starts an unsafe block as the expression being dispatched.
+    let unsafety = if input.peek(Token![unsafe]) && !input.peek2(token::Brace) {
+        Some(input.parse()?)
+    } else {
+        None
+    };
+    let input_expr = input.parse()?;
+    let _ = input.parse::<Token![:]>()?;
+    let from_type = input.parse()?;
+    let bulk = parse_bulk_slice(bulk, &from_type)?;
+    let _ = input.parse::<Token![->]>()?;
+    let to_type = input.parse()?;
+    let arms_content;
+    let _ = braced!(arms_content in input);
+    let mut arms = Vec::new();
+    while !arms_content.is_empty() {
+        let arm = parse_match_arm(&arms_content, bulk.as_ref())?;
+        if arms_content.is_empty() {
+            arms.push(arm);
+            break;
+        }
+        if match_arm_requires_comma(&arm.body) {
+            let _ = arms_content.parse::<Token![,]>()?;
+        } else {
+            let _ = arms_content.parse::<Option<Token![,]>>()?;
+        }
+        arms.push(arm);
+    }
+    let _ = input.parse::<Option<Token![,]>>()?;
+    let expr = SpecializedDispatchExpr {
+        selected_arm,
+        try_arms,
+        control_flow,
+        unsafety,
+        generic_params: None,
+        lifted,
+        closed,
+        bulk,
+        from_type,
+        to_type,
+        arms,
+        input_expr: Some(input_expr),
+        extra_args: Vec::new(),
+    }
+    .into_element_types()?;
+    expr.validate()?;
+    expr.check_duplicate_arms()?;
+    Ok(expr)
+}
+
+/// Writes tokens of a type to the string with conventional spacing, e.g. `Vec<&'a str>` rather
+/// than `Vec < & 'a str >` as formatting the token stream would give.
+fn write_type_tokens(tokens: TokenStream2, out: &mut String) {
+    let mut last_word = None;
+    for token in tokens {
+        match token {
+            TokenTree::Ident(_) | TokenTree::Literal(_) => {
+                if last_word.is_some() {
+                    out.push(' ');
+                }
+                let word = token.to_string();
+                out.push_str(&word);
+                last_word = Some(word);
+                continue;
+            }
+            TokenTree::Punct(punct) => match punct.as_char() {
+                c @ (',' | ';') => {
+                    out.push(c);
+                    out.push(' ');
+                }
+                c @ ('+' | '=') => {
+                    out.push(' ');
+                    out.push(c);
+                    out.push(' ');
+                }
+                c => out.push(c),
+            },
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                if matches!(last_word.as_deref(), Some("mut" | "dyn" | "impl")) {
+                    out.push(' ');
+                }
+                out.push_str(open);
+                write_type_tokens(group.stream(), out);
+                out.push_str(close);
+            }
+        }
+        last_word = None;
+    }
+}
+
+/// Returns the label reported for an arm in `#[selected_arm]` mode. This is the name given to the
+/// arm if there is one, or the type of the arm otherwise.
+fn arm_label(arm: &DispatchArmExpr) -> String {
+    match &arm.label {
+        Some(label) => label.to_string(),
+        None => {
+            let mut label = String::new();
+            write_type_tokens(arm.input.ty().to_token_stream(), &mut label);
+            label
+        }
+    }
+}
+
+/// Returns whether the attribute is `#[cfg(...)]`, which gates the whole arm rather than the
+/// generated method.
+fn is_cfg_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("cfg")
+}
+
+/// Returns `#[cfg(...)]` attributes of an arm formatted for comparison, so that arms for the same
+/// type under different configurations aren't considered the same.
+fn arm_cfg(arm: &DispatchArmExpr) -> String {
+    arm.attrs
+        .iter()
+        .filter(|attr| is_cfg_attr(attr))
+        .map(|attr| attr.to_token_stream().to_string())
+        .collect()
+}
+
+/// Token of a type, used for matching types against types of arms.
+#[derive(Debug, Eq, PartialEq)]
+enum TypeToken {
+    Word(String),
+    Punct(char),
+    Group(Delimiter, Vec<TypeToken>),
+}
+
+/// Converts tokens of a type for matching. Lifetimes are dropped, since they don't affect which arm
+/// is selected.
+fn type_tokens(tokens: TokenStream2) -> Vec<TypeToken> {
+    let mut converted = Vec::new();
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                let _ = tokens.next();
+            }
+            TokenTree::Punct(punct) => converted.push(TypeToken::Punct(punct.as_char())),
+            TokenTree::Ident(_) | TokenTree::Literal(_) => {
+                converted.push(TypeToken::Word(token.to_string()))
+            }
+            TokenTree::Group(group) => converted.push(TypeToken::Group(
+                group.delimiter(),
+                type_tokens(group.stream()),
+            )),
+        }
+    }
+    converted
+}
+
+/// Returns whether the pattern matches the type, where each of the generic parameters in the
+/// pattern matches any non-empty sequence of tokens.
+fn match_type_tokens(pattern: &[TypeToken], ty: &[TypeToken], params: &[String]) -> bool {
+    match pattern.split_first() {
+        None => ty.is_empty(),
+        Some((TypeToken::Word(word), rest)) if params.contains(word) => {
+            (1..=ty.len()).any(|len| match_type_tokens(rest, &ty[len..], params))
+        }
+        Some((TypeToken::Group(delimiter, inner), rest)) => match ty.split_first() {
+            Some((TypeToken::Group(ty_delimiter, ty_inner), ty_rest)) => {
+                delimiter == ty_delimiter
+                    && match_type_tokens(inner, ty_inner, params)
+                    && match_type_tokens(rest, ty_rest, params)
+            }
+            _ => false,
+        },
+        Some((token, rest)) => {
+            ty.first() == Some(token) && match_type_tokens(rest, &ty[1..], params)
+        }
+    }
+}
+
+/// Returns names of type and const parameters, which are matched against types.
+fn type_param_names<'a>(params: impl IntoIterator<Item = &'a GenericParam>) -> Vec<String> {
+    params
+        .into_iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.to_string()),
+            GenericParam::Const(param) => Some(param.ident.to_string()),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect()
+}
+
+/// Returns whether the pattern with the given generic parameters matches the type.
+fn covers_type<'a>(
+    params: impl IntoIterator<Item = &'a GenericParam>,
+    pattern: &Type,
+    ty: &Type,
+) -> bool {
+    match_type_tokens(
+        &type_tokens(pattern.to_token_stream()),
+        &type_tokens(ty.to_token_stream()),
+        &type_param_names(params),
+    )
+}
+
+/// Returns the default arm and its type parameter if the default arm accepts any type satisfying
+/// the bounds of its only generic parameter, e.g. `default fn <T: Display>(v: T)`.
+fn default_arm_type_param(arms: &[DispatchArmExpr]) -> Option<(&DispatchArmExpr, &TypeParam)> {
+    let default_arm = arms.iter().find(|arm| arm.default.is_some())?;
+    let generic_params = default_arm.generic_params.as_ref()?;
+    let Some(GenericParam::Type(type_param)) = generic_params.first() else {
+        return None;
+    };
+    let accepts_param = generic_params.len() == 1
+        && matches!(default_arm.input.ty(), Type::Path(path) if path.path.is_ident(&type_param.ident));
+    accepts_param.then_some((default_arm, type_param))
+}
+
+/// Replaces the given identifier in the tokens, e.g. the type parameter of the default arm with
+/// `Self`.
+fn replace_ident(tokens: TokenStream2, ident: &Ident, replacement: &TokenStream2) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .flat_map(|token| match token {
+            TokenTree::Ident(ref token_ident) if token_ident == ident => replacement.clone(),
+            TokenTree::Group(group) => {
+                let mut replaced = proc_macro2::Group::new(
+                    group.delimiter(),
+                    replace_ident(group.stream(), ident, replacement),
+                );
+                replaced.set_span(group.span());
+                TokenTree::Group(replaced).into()
+            }
+            token => token.into(),
+        })
+        .collect()
+}
+
+/// Returns whether the tokens contain the given identifier, e.g. the type parameter of the default
+/// arm.
+fn contains_ident(tokens: TokenStream2, ident: &Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(token_ident) => token_ident == *ident,
+        TokenTree::Group(group) => contains_ident(group.stream(), ident),
+        _ => false,
+    })
+}
+
+/// Returns whether extra arguments or the return type of the dispatch name the type parameter of
+/// the default arm.
+fn signature_names_param(extra_args: &[FnArg], return_type: &Type, param: &TypeParam) -> bool {
+    extra_args
+        .iter()
+        .map(|arg| &arg.ty)
+        .chain([return_type])
+        .any(|ty| contains_ident(ty.to_token_stream(), &param.ident))
+}
+
+/// Collects named lifetimes in the type in order of appearance, e.g. `'a` in `&'a str`, except for
+/// `'static`, `'_` and lifetimes bound by `for<'a>` within the type.
+fn collect_lifetimes(ty: &Type, lifetimes: &mut Vec<Lifetime>) {
+    FreeLifetimes {
+        lifetimes,
+        bound: Vec::new(),
+    }
+    .visit_type(ty);
+}
+
+/// Finds named lifetimes which aren't bound by `for<'a>`, see `collect_lifetimes`.
+struct FreeLifetimes<'a> {
+    lifetimes: &'a mut Vec<Lifetime>,
+    /// Lifetimes bound by the enclosing `for<...>` binders.
+    bound: Vec<Ident>,
+}
+
+impl FreeLifetimes<'_> {
+    /// Visits a node within the scope of the lifetimes bound by its `for<...>` binder.
+    fn with_bound(&mut self, binder: Option<&syn::BoundLifetimes>, visit: impl FnOnce(&mut Self)) {
+        let len = self.bound.len();
+        self.bound.extend(binder.into_iter().flat_map(|binder| {
+            binder.lifetimes.iter().filter_map(|param| match param {
+                GenericParam::Lifetime(param) => Some(param.lifetime.ident.clone()),
+                _ => None,
+            })
+        }));
+        visit(self);
+        self.bound.truncate(len);
+    }
+}
+
+impl<'ast> Visit<'ast> for FreeLifetimes<'_> {
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        let ident = &lifetime.ident;
+        if ident != "static"
+            && ident != "_"
+            && !self.bound.contains(ident)
+            && !self
+                .lifetimes
+                .iter()
+                .any(|lifetime| lifetime.ident == *ident)
+        {
+            self.lifetimes.push(lifetime.clone());
+        }
+    }
+
+    fn visit_type_bare_fn(&mut self, ty: &'ast syn::TypeBareFn) {
+        self.with_bound(ty.lifetimes.as_ref(), |this| {
+            visit::visit_type_bare_fn(this, ty)
+        });
+    }
+
+    fn visit_trait_bound(&mut self, bound: &'ast syn::TraitBound) {
+        self.with_bound(bound.lifetimes.as_ref(), |this| {
+            visit::visit_trait_bound(this, bound)
+        });
+    }
+}
+
+/// Returns lifetime parameters of the function generated for an arm, which are named lifetimes in
+/// the given types of its signature. The helper trait and functions are local items, so they can't
+/// use lifetimes of the enclosing function, e.g. in `ctx: &'a Context` and `-> &'a str`. Instead,
+/// they are introduced on the function and inferred at the call. Lifetimes declared by the given
+/// generic parameters, i.e. of the arm or the dispatch, are left out.
+fn arm_fn_lifetimes<'a>(
+    types: impl IntoIterator<Item = &'a Type>,
+    declared: impl IntoIterator<Item = &'a GenericParam> + Clone,
+) -> Vec<Lifetime> {
+    let mut lifetimes = Vec::new();
+    for ty in types {
+        collect_lifetimes(ty, &mut lifetimes);
+    }
+    lifetimes.retain(|lifetime| {
+        !declared.clone().into_iter().any(|param| {
+            matches!(param, GenericParam::Lifetime(param) if param.lifetime.ident == lifetime.ident)
+        })
+    });
+    lifetimes
+}
+
+/// Generates generic parameters from the lifetimes introduced for an arm and the given generic
+/// parameters, ordering lifetimes first.
+fn generate_generics<'a>(
+    lifetimes: Vec<Lifetime>,
+    params: impl IntoIterator<Item = &'a GenericParam>,
+) -> Option<TokenStream2> {
+    let mut params: Vec<GenericParam> = lifetimes
+        .into_iter()
+        .map(|lifetime| GenericParam::Lifetime(LifetimeParam::new(lifetime)))
+        .chain(params.into_iter().cloned())
+        .collect();
+    params.sort_by_key(|param| !matches!(param, GenericParam::Lifetime(_)));
+    (!params.is_empty()).then(|| quote! {<#(#params),*>})
+}
+
+/// Generates generic arguments passing the given generic parameters by name, followed by the given
+/// number of inferred arguments, e.g. `::<W, _>`. Lifetimes are left to be inferred.
+fn generate_turbofish<'a>(
+    params: impl IntoIterator<Item = &'a GenericParam>,
+    inferred: usize,
+) -> Option<TokenStream2> {
+    let names: Vec<_> = params
+        .into_iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(&param.ident),
+            GenericParam::Const(param) => Some(&param.ident),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let inferred = std::iter::repeat_n(quote! {_}, inferred);
+    (!names.is_empty()).then(|| quote! {::<#(#names,)* #(#inferred),*>})
+}
+
+/// Generates the path of the helper trait with generic parameters lifted by `#[lift(...)]` option
+/// passed by name, e.g. `SpecializedDispatchCall<S>`.
+fn generate_trait_path(trait_name: &Ident, dispatch: &SpecializedDispatchExpr) -> TokenStream2 {
+    let Some(lifted) = &dispatch.lifted else {
+        return trait_name.to_token_stream();
+    };
+    let args = lifted.iter().map(|param| match param {
+        GenericParam::Type(param) => param.ident.to_token_stream(),
+        GenericParam::Const(param) => param.ident.to_token_stream(),
+        GenericParam::Lifetime(param) => param.lifetime.to_token_stream(),
+    });
+    quote! {#trait_name<#(#args),*>}
+}
+
+/// Returns the type of the dispatched value taken by the helper trait, which is `Self` unless slices
+/// are dispatched on their element type by `#[bulk]` option.
+fn dispatched_arg_type(dispatch: &SpecializedDispatchExpr) -> TokenStream2 {
+    match &dispatch.bulk {
+        Some(bulk) => bulk.slice_of(quote! {Self}),
+        None => quote! {Self},
+    }
+}
+
+/// Generates local helper trait declaration that will be used for specialized dispatch.
+///
+/// The dispatched value is taken as `Self` rather than a type parameter, so that lifetimes elided
+/// in the type of an arm (e.g. `&[T]`) are introduced only once in the implementation.
+///
+/// If the default arm accepts any type satisfying the bounds of its type parameter, the bounds and
+/// its where clause are carried onto the `dispatch` method with the type parameter replaced by
+/// `Self`. Then, the dispatched type is checked against the bounds at the call, rather than failing
+/// to implement the trait. They are put on the method rather than the trait, so that arms for
+/// types not satisfying them still compile, which are warned by
+/// `generate_default_bounds_warnings` instead.
+fn generate_trait_declaration(
+    trait_name: &Ident,
+    dispatch: &SpecializedDispatchExpr,
+    extra_args: &[FnArg],
+) -> TokenStream2 {
+    let SpecializedDispatchExpr {
+        selected_arm,
+        unsafety,
+        generic_params,
+        lifted,
+        to_type: return_type,
+        arms,
+        ..
+    } = dispatch;
+    let lifetimes = arm_fn_lifetimes(
+        extra_args.iter().map(|arg| &arg.ty).chain([return_type]),
+        generic_params.iter().chain(lifted).flatten(),
+    );
+    let trait_generics = generate_generics(Vec::new(), lifted.iter().flatten());
+    let generics = generate_generics(lifetimes, generic_params.iter().flatten());
+    let input_type = dispatched_arg_type(dispatch);
+    let input_arg = dispatch
+        .input_expr
+        .is_some()
+        .then(|| quote! {_: #input_type});
+    // Elements of slices must be sized.
+    let supertraits = dispatch
+        .bulk
+        .is_some()
+        .then(|| quote! {: ::core::marker::Sized});
+    // Extra arguments and the return type may name the type parameter of the default arm, which
+    // is the implementing type of the trait.
+    let default_param = default_arm_type_param(arms);
+    let replace_default_param = |ty: &Type| match default_param {
+        Some((_, param)) => replace_ident(ty.to_token_stream(), &param.ident, &quote! {Self}),
+        None => ty.to_token_stream(),
+    };
+    let args = input_arg
+        .into_iter()
+        .chain(extra_args.iter().map(|FnArg { name, ty, .. }| {
+            let ty = replace_default_param(ty);
+            quote! {#name: #ty}
+        }));
+    let signature_return_type = replace_default_param(return_type);
+    let selected_arm_fn = selected_arm.then(|| {
+        quote! {
+            fn selected_arm() -> &'static str;
+        }
+    });
+    let where_clause = default_param.and_then(|(default_arm, param)| {
+        let bounds = &param.bounds;
+        let mut predicates = Vec::new();
+        if !bounds.is_empty() {
+            // Bounds may name the type parameter as well, e.g. `T: PartialEq<T>`.
+            let bounds = replace_ident(bounds.to_token_stream(), &param.ident, &quote! {Self});
+            predicates.push(quote! {Self: #bounds});
+        }
+        // The type parameter is implicitly sized, unlike `Self`.
+        if signature_names_param(extra_args, return_type, param)
+            && !bounds.iter().any(is_maybe_bound)
+        {
+            predicates.push(quote! {Self: ::core::marker::Sized});
+        }
+        if let Some(where_clause) = &default_arm.where_clause {
+            predicates.extend(where_clause.predicates.iter().map(|predicate| {
+                replace_ident(predicate.to_token_stream(), &param.ident, &quote! {Self})
+            }));
+        }
+        (!predicates.is_empty()).then(|| quote! {where #(#predicates),*})
+    });
+    quote! {
+        trait #trait_name #trait_generics #supertraits {
+            #unsafety fn dispatch #generics(#(#args),*) -> #signature_return_type #where_clause;
+            #selected_arm_fn
+        }
+    }
+}
+
+/// Returns the identifier of the static counting selections of arms.
+fn counters_ident() -> Ident {
+    Ident::new("COUNTERS", Span2::mixed_site())
+}
+
+/// Generates the static counting selections of each arm if `counters` feature is enabled.
+fn generate_counters(arms: &[DispatchArmExpr]) -> Option<TokenStream2> {
+    if !cfg!(feature = "counters") {
+        return None;
+    }
+    let counters = counters_ident();
+    let num_arms = arms.len();
+    let labels = arms.iter().map(arm_label);
+    Some(quote! {
+        static #counters: ::specialized_dispatch::counters::DispatchCounters<#num_arms> =
+            ::specialized_dispatch::counters::DispatchCounters::new(
+                ::core::file!(),
+                ::core::line!(),
+                ::core::column!(),
+                [#(#labels),*],
+            );
+    })
+}
+
+/// Generates the body of an arm, which is instrumented depending on the enabled features:
+///
+/// - `counters`: Counts selections of the arm.
+/// - `tracing`: Enters a span with location of the dispatch, label of the arm and the dispatched
+///   type, which is given by `input_type`.
+///
+/// If `#[try_arms]` is given, the value of the body is wrapped in `Ok`.
+fn generate_arm_body(
+    dispatch: &SpecializedDispatchExpr,
+    arm_index: usize,
+    arm: &DispatchArmExpr,
+    input_type: &TokenStream2,
+) -> TokenStream2 {
+    let body = &arm.body;
+    let body = if dispatch.try_arms {
+        quote! {::core::result::Result::Ok(#body)}
+    } else {
+        body.to_token_stream()
+    };
+    let counter = cfg!(feature = "counters").then(|| {
+        let counters = counters_ident();
+        quote! {
+            #counters.hit(#arm_index);
+        }
+    });
+    let span = cfg!(feature = "tracing").then(|| {
+        let span = Ident::new("span", Span2::mixed_site());
+        let label = arm_label(arm);
+        quote! {
+            let #span = ::specialized_dispatch::__private::tracing::trace_span!(
+                "dispatch",
+                location = ::core::concat!(
+                    ::core::file!(),
+                    ":",
+                    ::core::line!(),
+                    ":",
+                    ::core::column!()
+                ),
+                arm = #label,
+                dispatched_type = ::core::any::type_name::<#input_type>(),
+            )
+            .entered();
+        }
+    });
+    if counter.is_none() && span.is_none() {
+        return body;
+    }
+    quote! {
+        #counter
+        #span
+        #body
+    }
+}
+
+/// Generates the lint attribute and the body of an arm for an `unsafe` dispatch. Bodies are unsafe
+/// contexts only if their arms are marked `unsafe`, regardless of the edition.
+fn generate_unsafe_body(
+    unsafety: Option<&Token![unsafe]>,
+    arm: &DispatchArmExpr,
+    body: TokenStream2,
+) -> (Option<TokenStream2>, TokenStream2) {
+    if unsafety.is_none() {
+        return (None, body);
+    }
+    let attr = quote! {#[deny(unsafe_op_in_unsafe_fn)]};
+    match &arm.unsafety {
+        Some(arm_unsafety) => (Some(attr), quote! {#arm_unsafety { #body }}),
+        None => (Some(attr), body),
+    }
+}
+
+/// Generates implementation of the helper trait for specialized dispatch arms. This covers both
+/// generic case(s) and concrete case(s).
+///
+/// Attributes of the arm are forwarded to the dispatch method, except for `#[cfg(...)]` which is
+/// put on the implementation, since the method can't be left out of it.
+fn generate_trait_implementation(
+    trait_name: &Ident,
+    dispatch: &SpecializedDispatchExpr,
+    arm: &DispatchArmExpr,
+    body: TokenStream2,
+) -> TokenStream2 {
+    let unsafety = dispatch.unsafety.as_ref();
+    let return_type = &dispatch.to_type;
+    let DispatchArmExpr {
+        attrs,
+        default,
+        generic_params,
+        input,
+        extra_args,
+        where_clause,
+        ..
+    } = arm;
+    let (cfg_attrs, fn_attrs): (Vec<_>, Vec<_>) = attrs.iter().partition(|attr| is_cfg_attr(attr));
+    let generics = generate_generics(
+        Vec::new(),
+        dispatch.lifted.iter().chain(generic_params).flatten(),
+    );
+    let trait_path = generate_trait_path(trait_name, dispatch);
+    let input_type = input.ty();
+    let input_arg = match input {
+        ArmInput::Value(FnArg {
+            r#mut: input_expr_mut,
+            name: input_expr_name,
+            ..
+        }) => {
+            let input_type = dispatched_arg_type(dispatch);
+            Some(quote! {#input_expr_mut #input_expr_name: #input_type})
+        }
+        ArmInput::Type(_) => None,
+    };
+    let args = input_arg
+        .into_iter()
+        .chain(extra_args.iter().map(|arg| quote! {#arg}));
+    let lifetimes = arm_fn_lifetimes(
+        extra_args.iter().map(|arg| &arg.ty).chain([return_type]),
+        generic_params
+            .iter()
+            .chain(&dispatch.generic_params)
+            .chain(&dispatch.lifted)
+            .flatten(),
+    );
+    let fn_generics = generate_generics(lifetimes, dispatch.generic_params.iter().flatten());
+    // Methods of default arms are located at `default` keyword, so that errors about specialization
+    // (e.g. when the feature isn't enabled) point at it rather than at the whole macro call. They
+    // still resolve at the call site, so that they are treated as generated code by lints.
+    let span = default.map_or_else(Span2::call_site, |default| {
+        Span2::call_site().located_at(default.span)
+    });
+    let selected_arm_fn = dispatch.selected_arm.then(|| {
+        let label = arm_label(arm);
+        quote_spanned! {span=>
+            #default fn selected_arm() -> &'static str {
+                #label
+            }
+        }
+    });
+    let (unsafe_attr, body) = generate_unsafe_body(unsafety, arm, body);
+    let dispatch_fn = quote_spanned! {span=>
+        #(#fn_attrs)*
+        #unsafe_attr
+        #default #unsafety fn dispatch #fn_generics(#(#args),*) -> #return_type {
+            #body
+        }
+    };
+    quote! {
+        #(#cfg_attrs)*
+        impl #generics #trait_path for #input_type #where_clause {
+            #dispatch_fn
+            #selected_arm_fn
+        }
+    }
+}
+
+/// Returns the default arm if newtypes deriving `SpecializedDispatch` are dispatched as their inner
+/// types, which requires `derive` feature. Forwarding to the inner type is done by specializing on
+/// the `SpecializedDispatch` trait, hence it's possible only if the default arm accepts any type
+/// without bounds, and its type parameter isn't named by extra arguments or the return type.
+fn newtype_forwarding_arm(dispatch: &SpecializedDispatchExpr) -> Option<&DispatchArmExpr> {
+    // Slices of newtypes can't be converted to slices of their inner types.
+    if !cfg!(feature = "derive") || dispatch.bulk.is_some() {
+        return None;
+    }
+    let (default_arm, param) = default_arm_type_param(&dispatch.arms)?;
+    (param.bounds.is_empty()
+        && default_arm.where_clause.is_none()
+        && !signature_names_param(&default_arm.extra_args, &dispatch.to_type, param))
+    .then_some(default_arm)
+}
+
+/// Generates the forwarding of newtypes deriving `SpecializedDispatch` to their inner types. The
+/// default arm is moved to a second helper trait, to which the helper trait falls back for types
+/// without an arm. Newtypes specialize the second trait by dispatching their inner value, so that
+/// arms for newtypes themselves are still preferred.
+fn generate_newtype_forwarding(
+    trait_name: &Ident,
+    fallback_name: &Ident,
+    dispatch: &SpecializedDispatchExpr,
+    default_arm: &DispatchArmExpr,
+) -> TokenStream2 {
+    let unsafety = dispatch.unsafety.as_ref();
+    let return_type = &dispatch.to_type;
+    let extra_args = &default_arm.extra_args;
+    let cfg_attrs = default_arm.attrs.iter().filter(|attr| is_cfg_attr(attr));
+    let cfg_attrs = quote! {#(#cfg_attrs)*};
+
+    let value = Ident::new("value", Span2::mixed_site());
+    let arg_names: Vec<_> = (0..extra_args.len())
+        .map(|i| Ident::new(&format!("arg{}", i), Span2::mixed_site()))
+        .collect();
+    let arg_types = extra_args.iter().map(|arg| &arg.ty);
+    let input_arg = dispatch.input_expr.is_some().then(|| quote! {#value: Self});
+    let args: Vec<_> = input_arg
+        .iter()
+        .cloned()
+        .chain(
+            arg_names
+                .iter()
+                .zip(arg_types)
+                .map(|(name, ty)| quote! {#name: #ty}),
+        )
+        .collect();
+    let input_value: Vec<_> = input_arg.iter().map(|_| quote! {#value}).collect();
+    let inner_value: Vec<_> = input_arg
+        .iter()
+        .map(|_| quote! {::specialized_dispatch::SpecializedDispatch::into_inner(#value)})
+        .collect();
+
+    let lifetimes = arm_fn_lifetimes(
+        extra_args.iter().map(|arg| &arg.ty).chain([return_type]),
+        dispatch
+            .generic_params
+            .iter()
+            .chain(&dispatch.lifted)
+            .flatten(),
+    );
+    let fn_generics = generate_generics(lifetimes, dispatch.generic_params.iter().flatten());
+    let turbofish = generate_turbofish(dispatch.generic_params.iter().flatten(), 0);
+    let trait_path = generate_trait_path(trait_name, dispatch);
+    let fallback_path = generate_trait_path(fallback_name, dispatch);
+    let unsafe_call = |call: TokenStream2| match unsafety {
+        Some(unsafety) => quote! {#unsafety { #call }},
+        None => call,
+    };
+
+    let param = Ident::new("T", Span2::mixed_site());
+    let param_generics = generate_generics(
+        Vec::new(),
+        dispatch
+            .lifted
+            .iter()
+            .flatten()
+            .chain([&parse_quote!(#param)]),
+    );
+    let fallback_call = unsafe_call(quote! {
+        <#param as #fallback_path>::dispatch #turbofish(#(#input_value,)* #(#arg_names),*)
+    });
+    let fallback_selected_arm = dispatch.selected_arm.then(|| {
+        quote! {
+            default fn selected_arm() -> &'static str {
+                <#param as #fallback_path>::selected_arm()
+            }
+        }
+    });
+
+    let newtype = Ident::new("W", Span2::mixed_site());
+    let newtype_generics = generate_generics(
+        Vec::new(),
+        dispatch
+            .lifted
+            .iter()
+            .flatten()
+            .chain([&parse_quote!(#newtype: ::specialized_dispatch::SpecializedDispatch)]),
+    );
+    let inner = quote! {<#newtype as ::specialized_dispatch::SpecializedDispatch>::Inner};
+    let inner_call = unsafe_call(quote! {
+        <#inner as #trait_path>::dispatch #turbofish(#(#inner_value,)* #(#arg_names),*)
+    });
+    let inner_selected_arm = dispatch.selected_arm.then(|| {
+        quote! {
+            fn selected_arm() -> &'static str {
+                <#inner as #trait_path>::selected_arm()
+            }
+        }
+    });
+
+    // Located at `default` keyword as the default arm, see `generate_trait_implementation`.
+    let span = default_arm
+        .default
+        .map_or_else(Span2::call_site, |default| {
+            Span2::call_site().located_at(default.span)
+        });
+    let fallback_decl = generate_trait_declaration(fallback_name, dispatch, extra_args);
+    quote_spanned! {span=>
+        #fallback_decl
+        #cfg_attrs
+        impl #param_generics #trait_path for #param {
+            default #unsafety fn dispatch #fn_generics(#(#args),*) -> #return_type {
+                #fallback_call
+            }
+            #fallback_selected_arm
+        }
+        #cfg_attrs
+        impl #newtype_generics #fallback_path for #newtype {
+            #unsafety fn dispatch #fn_generics(#(#args),*) -> #return_type {
+                #inner_call
+            }
+            #inner_selected_arm
+        }
+    }
+}
+
+/// Generates the arm used for types without an arm when the set of dispatched types is closed
+/// without a default arm. It's never called, since the dispatched type and each type in the set are
+/// checked to have an arm by `generate_closed_check`.
+fn generate_closed_fallback_arm(type_only: bool, extra_args: &[FnArg]) -> DispatchArmExpr {
+    let tpl = Ident::new("T", Span2::mixed_site());
+    let input = if type_only {
+        ArmInput::Type(parse_quote!(#tpl))
+    } else {
+        ArmInput::Value(FnArg {
+            r#mut: None,
+            name: FnArgName::Underscore(Default::default()),
+            ty: parse_quote!(#tpl),
+        })
+    };
+    let extra_args = extra_args
+        .iter()
+        .map(|arg| FnArg {
+            r#mut: None,
+            name: FnArgName::Underscore(Default::default()),
+            ty: arg.ty.clone(),
+        })
+        .collect();
+    DispatchArmExpr {
+        attrs: Vec::new(),
+        default: Some(Default::default()),
+        unsafety: None,
+        label: None,
+        generic_params: Some(parse_quote!(#tpl)),
+        input,
+        extra_args,
+        where_clause: None,
+        body: parse_quote!(::core::unreachable!()),
+    }
+}
+
+/// Generates the check that the dispatched type implements the trait of the closed set declared by
+/// `closed_set` macro, which fails to type check for other types. Without a default arm, it also
+/// checks that each type in the set has an arm, by implementing `ClosedSetArm` for a marker type
+/// for the types of the arms, which is required for the types in the set by the trait.
+fn generate_closed_check(dispatch: &SpecializedDispatchExpr) -> Option<TokenStream2> {
+    let closed = dispatch.closed.as_ref()?;
+    let path = &closed.path;
+    let span = Span2::call_site().located_at(closed.keyword.span());
+    let check_fn = Ident::new("check_closed_set", Span2::mixed_site().located_at(span));
+    let tpl = Ident::new("T", Span2::mixed_site().located_at(span));
+    let from_type = &dispatch.from_type;
+    if dispatch.arms.iter().any(|arm| arm.default.is_some()) {
+        return Some(quote_spanned! {span=>
+            fn #check_fn<#tpl: ?::core::marker::Sized + #path>() {}
+            #check_fn::<#from_type>();
+        });
+    }
+    let marker = Ident::new("ClosedSetArms", Span2::mixed_site().located_at(span));
+    let arm_impls = dispatch.arms.iter().map(|arm| {
+        let ty = arm.input.ty();
+        let cfg_attrs = arm.attrs.iter().filter(|attr| is_cfg_attr(attr));
+        // Lifted parameters are declared only if they are used by the type of the arm, since they
+        // would be unconstrained otherwise.
+        let lifted = dispatch.lifted.iter().flatten().filter(|param| {
+            let ident = match param {
+                GenericParam::Type(param) => &param.ident,
+                GenericParam::Const(param) => &param.ident,
+                GenericParam::Lifetime(param) => &param.lifetime.ident,
+            };
+            contains_ident(ty.to_token_stream(), ident)
+        });
+        let generics = generate_generics(
+            Vec::new(),
+            lifted.chain(arm.generic_params.iter().flatten()),
+        );
+        let where_clause = &arm.where_clause;
+        let span = Span2::call_site().located_at(ty.span());
+        quote_spanned! {span=>
+            #(#cfg_attrs)*
+            impl #generics ::specialized_dispatch::__private::ClosedSetArm<#ty> for #marker
+            #where_clause
+            {}
+        }
+    });
+    Some(quote_spanned! {span=>
+        struct #marker;
+        #(#arm_impls)*
+        fn #check_fn<#tpl: ?::core::marker::Sized + #path>() {
+            <#tpl as #path>::__closed_set_arms::<#marker>();
+        }
+        #check_fn::<#from_type>();
+    })
+}
+
+/// Modules of the standard library defining types which are also available by their names, with
+/// the names of those types, i.e. primitive types and types in the prelude.
+const PRELUDE_TYPE_PATHS: &[(&str, &[&str])] = &[
+    ("core::primitive::", PRIMITIVE_TYPES),
+    ("std::primitive::", PRIMITIVE_TYPES),
+    ("core::option::", &["Option"]),
+    ("std::option::", &["Option"]),
+    ("core::result::", &["Result"]),
+    ("std::result::", &["Result"]),
+    ("alloc::boxed::", &["Box"]),
+    ("std::boxed::", &["Box"]),
+    ("alloc::string::", &["String"]),
+    ("std::string::", &["String"]),
+    ("alloc::vec::", &["Vec"]),
+    ("std::vec::", &["Vec"]),
+];
+
+const PRIMITIVE_TYPES: &[&str] = &[
+    "bool", "char", "str", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16",
+    "u32", "u64", "u128", "usize",
+];
+
+/// Removes the path of the given item wherever it's a whole path in the name of a type, leaving
+/// the name of the item.
+fn remove_item_path(name: &str, module: &str, item: &str) -> String {
+    let is_path_char = |c: char| c.is_alphanumeric() || c == '_' || c == ':';
+    let path = format!("{}{}", module, item);
+    let mut removed = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(pos) = rest.find(&path) {
+        removed.push_str(&rest[..pos]);
+        let after = &rest[pos + path.len()..];
+        if removed.ends_with(is_path_char) || after.starts_with(is_path_char) {
+            removed.push_str(&path);
+        } else {
+            removed.push_str(item);
+        }
+        rest = after;
+    }
+    removed.push_str(rest);
+    removed
+}
+
+/// Returns the name of a type normalized for comparison, so that e.g. `core::primitive::u8` and
+/// `u8` have the same name. Type aliases can't be resolved, since their definitions aren't visible
+/// to the macro.
+fn normalized_type_name(ty: &Type) -> String {
+    let mut name = String::new();
+    write_type_tokens(ty.to_token_stream(), &mut name);
+    for (module, items) in PRELUDE_TYPE_PATHS {
+        for item in *items {
+            name = remove_item_path(&name, &format!("::{}", module), item);
+            name = remove_item_path(&name, module, item);
+        }
+    }
+    name
+}
+
+/// Generates a warning at the given span, which is reported as the use of a deprecated function of
+/// the given name. Since warnings can't be emitted by procedural macros on stable, this is done by
+/// calling a deprecated function with the warning as its note. It can be silenced with
+/// `#[allow(deprecated)]` like any other deprecation warning.
+fn generate_warning(name: &str, at: Span2, warning: &str) -> TokenStream2 {
+    let fn_name = Ident::new(name, Span2::mixed_site().located_at(at));
+    let span = Span2::call_site().located_at(at);
+    quote_spanned! {span=>
+        {
+            #[deprecated(note = #warning)]
+            fn #fn_name() {}
+            #fn_name();
+        }
+    }
+}
+
+/// Returns whether the arm at the given index is never selected, since an earlier arm has the same
+/// type after normalization. If `exact` is false, arms whose types are the same without
+/// normalization are skipped, since they are rejected while parsing.
+fn is_shadowed_arm(arms: &[&DispatchArmExpr], i: usize, exact: bool) -> bool {
+    let arm = arms[i];
+    let ty = arm.input.ty();
+    let name = normalized_type_name(ty);
+    arms[..i].iter().any(|earlier| {
+        arm_cfg(earlier) == arm_cfg(arm)
+            && earlier.generic_params.to_token_stream().to_string()
+                == arm.generic_params.to_token_stream().to_string()
+            && normalized_type_name(earlier.input.ty()) == name
+            && (exact || earlier.input.ty() != ty)
+    })
+}
+
+/// Generates warnings for arms which are never selected, as determined by `is_shadowed_arm`.
+fn generate_shadowed_arm_warnings(arms: &[&DispatchArmExpr], exact: bool) -> TokenStream2 {
+    let mut warnings = TokenStream2::new();
+    for (i, arm) in arms.iter().enumerate() {
+        if is_shadowed_arm(arms, i, exact) {
+            let ty = arm.input.ty();
+            warnings.extend(generate_warning(
+                "unreachable_arm",
+                ty.span(),
+                &format!(
+                    "this arm is never selected, since an earlier arm has the same type `{}`",
+                    normalized_type_name(ty)
+                ),
+            ));
+        }
+    }
+    warnings
+}
+
+/// Finds `return` expressions which return from the arm itself, i.e. which are not inside closures,
+/// async blocks or nested items.
+#[derive(Default)]
+struct ArmReturns(Vec<Span2>);
+
+impl<'ast> Visit<'ast> for ArmReturns {
+    fn visit_expr_return(&mut self, expr: &'ast syn::ExprReturn) {
+        self.0.push(expr.return_token.span);
+        visit::visit_expr_return(self, expr);
+    }
+
+    fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+    fn visit_expr_async(&mut self, _: &'ast syn::ExprAsync) {}
+
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+}
+
+/// Generates warnings for concrete arms which are never selected, since their types don't satisfy
+/// the bounds of the default arm, e.g. a `u8` arm with `default fn <T: MyTrait>(v: T)` if `u8`
+/// doesn't implement `MyTrait`. This is checked only if the default arm accepts any type satisfying
+/// its bounds, and the bounds can't name generic parameters of the dispatch.
+///
+/// The check relies on autoref-based method resolution: `check` of `SatisfiesDefaultBounds` is
+/// preferred if it's implemented for the type, otherwise deprecated `check` of `UnreachableArm` is
+/// called on the reference.
+fn generate_default_bounds_warnings(dispatch: &SpecializedDispatchExpr) -> TokenStream2 {
+    if dispatch.generic_params.is_some() || dispatch.lifted.is_some() {
+        return TokenStream2::new();
+    }
+    let Some((default_arm, param)) = default_arm_type_param(&dispatch.arms) else {
+        return TokenStream2::new();
+    };
+    let generic_params = &default_arm.generic_params;
+    let where_clause = &default_arm.where_clause;
+    if param.bounds.is_empty() && where_clause.is_none() {
+        return TokenStream2::new();
+    }
+
+    let probe = Ident::new("DefaultBoundsProbe", Span2::mixed_site());
+    let satisfies = Ident::new("SatisfiesDefaultBounds", Span2::mixed_site());
+    let unreachable = Ident::new("UnreachableArm", Span2::mixed_site());
+    let tpl = Ident::new("T", Span2::mixed_site());
+    let param = &param.ident;
+    let warning =
+        "this arm is never selected, since its type doesn't satisfy the bounds of the default arm";
+    let checks: Vec<_> = dispatch
+        .arms
+        .iter()
+        .filter(|arm| arm.default.is_none() && arm.generic_params.is_none())
+        .filter(|arm| {
+            // Named lifetimes are declared only on methods of arms.
+            let mut lifetimes = Vec::new();
+            collect_lifetimes(arm.input.ty(), &mut lifetimes);
+            lifetimes.is_empty()
+        })
+        .map(|arm| {
+            let ty = arm.input.ty();
+            let cfg_attrs = arm.attrs.iter().filter(|attr| is_cfg_attr(attr));
+            let span = Span2::call_site().located_at(ty.span());
+            quote_spanned! {span=>
+                #(#cfg_attrs)*
+                (&#probe::<#ty>(::core::marker::PhantomData)).check();
+            }
+        })
+        .collect();
+    if checks.is_empty() {
+        return TokenStream2::new();
+    }
+    quote! {
+        {
+            struct #probe<#tpl: ?::core::marker::Sized>(::core::marker::PhantomData<#tpl>);
+            trait #satisfies {
+                fn check(&self) {}
+            }
+            impl<#generic_params> #satisfies for #probe<#param> #where_clause {}
+            trait #unreachable {
+                #[deprecated(note = #warning)]
+                fn check(&self) {}
+            }
+            impl<#tpl: ?::core::marker::Sized> #unreachable for &#probe<#tpl> {}
+            #(#checks)*
+        }
+    }
+}
+
+/// Generates warnings for `return` in arms, which returns from the arm rather than the enclosing
+/// function as it may seem. These are not generated with `#[control_flow]`, where returning
+/// `ControlFlow::Break` from an arm is the way to return from the enclosing function.
+fn generate_arm_return_warnings(dispatch: &SpecializedDispatchExpr) -> TokenStream2 {
+    let mut returns = ArmReturns::default();
+    if !dispatch.control_flow {
+        for arm in &dispatch.arms {
+            returns.visit_expr(&arm.body);
+        }
+    }
+    returns
+        .0
+        .into_iter()
+        .map(|span| {
+            generate_warning(
+                "return_in_arm",
+                span,
+                "`return` in an arm returns from the arm rather than the enclosing function, use \
+                 `#[control_flow]` option to return from the enclosing function",
+            )
+        })
+        .collect()
+}
+
+/// Generates the handling of the value returned from an arm with `#[control_flow]`, which returns
+/// from the enclosing function on `ControlFlow::Break`.
+fn generate_control_flow(dispatch: &SpecializedDispatchExpr, call: TokenStream2) -> TokenStream2 {
+    if !dispatch.control_flow {
+        return call;
+    }
+    let value = Ident::new("value", Span2::mixed_site());
+    quote! {
+        match #call {
+            ::core::ops::ControlFlow::Continue(#value) => #value,
+            ::core::ops::ControlFlow::Break(#value) => return #value,
+        }
+    }
+}
+
+/// Returns the span of calls to arms, which is located at `unsafe` keyword of an `unsafe` dispatch so
+/// that calls outside of an `unsafe` block are reported there.
+fn arm_call_span(unsafety: Option<&Token![unsafe]>) -> Span2 {
+    unsafety.map_or_else(Span2::call_site, |unsafety| {
+        Span2::call_site().located_at(unsafety.span)
+    })
+}
+
+/// Generates the dispatch call to the helper trait.
+fn generate_dispatch_call(trait_name: &Ident, dispatch: &SpecializedDispatchExpr) -> TokenStream2 {
+    let from_type = &dispatch.from_type;
+    let args = dispatch.input_expr.iter().chain(&dispatch.extra_args);
+    let trait_path = generate_trait_path(trait_name, dispatch);
+    let turbofish = generate_turbofish(dispatch.generic_params.iter().flatten(), 0);
+    let call = quote_spanned! {arm_call_span(dispatch.unsafety.as_ref())=>
+        <#from_type as #trait_path>::dispatch #turbofish(#(#args),*)
+    };
+    let call = generate_control_flow(dispatch, call);
+    if dispatch.selected_arm {
+        quote! {
+            (#call, <#from_type as #trait_path>::selected_arm())
+        }
+    } else {
+        call
+    }
+}
+
+impl ToTokens for SpecializedDispatchExpr {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let trait_name = Ident::new("SpecializedDispatchCall", Span2::mixed_site());
+        let fallback_name = Ident::new("SpecializedDispatchFallback", Span2::mixed_site());
+        let forwarding_arm = newtype_forwarding_arm(self);
+        let mut trait_impls = TokenStream2::new();
+        let mut extra_args = None;
+
+        for (i, arm) in self.arms.iter().enumerate() {
+            if arm.default.is_some() && extra_args.is_none() {
+                extra_args = Some(&arm.extra_args);
+            }
+            // The default arm is moved to the fallback trait if newtypes are forwarded.
+            let arm_trait_name = match forwarding_arm {
+                Some(default_arm) if std::ptr::eq(arm, default_arm) => &fallback_name,
+                _ => &trait_name,
+            };
+            trait_impls.extend(generate_trait_implementation(
+                arm_trait_name,
+                self,
+                arm,
+                generate_arm_body(self, i, arm, &quote! {Self}),
+            ));
+        }
+        if let Some(default_arm) = forwarding_arm {
+            trait_impls.extend(generate_newtype_forwarding(
+                &trait_name,
+                &fallback_name,
+                self,
+                default_arm,
+            ));
+        }
+
+        if self.closed.is_some() && extra_args.is_none() {
+            extra_args = self.arms.first().map(|arm| &arm.extra_args);
+            let fallback = generate_closed_fallback_arm(
+                self.input_expr.is_none(),
+                extra_args.map_or(&[], Vec::as_slice),
+            );
+            trait_impls.extend(generate_trait_implementation(
+                &trait_name,
+                self,
+                &fallback,
+                fallback.body.to_token_stream(),
+            ));
+        }
+        let closed_check = generate_closed_check(self);
+
+        let trait_decl =
+            generate_trait_declaration(&trait_name, self, extra_args.unwrap_or(&Vec::new()));
+
+        let dispatch_call = generate_dispatch_call(&trait_name, self);
+
+        let counters = generate_counters(&self.arms);
+
+        let arms: Vec<_> = self.arms.iter().collect();
+        let shadowed_arm_warnings = generate_shadowed_arm_warnings(&arms, false);
+        let arm_return_warnings = generate_arm_return_warnings(self);
+        let default_bounds_warnings = generate_default_bounds_warnings(self);
+
+        tokens.extend(quote! {
+            {
+                #counters
+                #trait_decl
+                #trait_impls
+                #shadowed_arm_warnings
+                #arm_return_warnings
+                #default_bounds_warnings
+                #closed_check
+                #dispatch_call
+            }
+        });
+    }
+}
+
+/// Downcast method used for trying an arm of `dynamic_dispatch` macro.
+#[derive(Debug, Eq, PartialEq)]
+enum Downcast<'a> {
+    /// `&T` arms, tried with `downcast_ref`.
+    Ref(&'a Type),
+    /// `&mut T` arms, tried with `downcast_mut`.
+    Mut(&'a Type),
+    /// `Box<T>` arms, tried with `downcast`.
+    Box(&'a Type),
+}
+
+impl<'a> Downcast<'a> {
+    /// Determines how the value should be downcast for the given type of an arm.
+    fn from_arm_type(ty: &'a Type) -> Result<Self> {
+        match ty {
+            Type::Reference(reference) if reference.mutability.is_some() => {
+                return Ok(Self::Mut(&reference.elem));
+            }
+            Type::Reference(reference) => return Ok(Self::Ref(&reference.elem)),
+            Type::Path(path) if path.qself.is_none() => {
+                let segment = path.path.segments.last();
+                if let Some(syn::PathSegment {
+                    ident,
+                    arguments: syn::PathArguments::AngleBracketed(args),
+                }) = segment
+                {
+                    if let (true, 1, Some(syn::GenericArgument::Type(elem))) =
+                        (ident == "Box", args.args.len(), args.args.first())
+                    {
+                        return Ok(Self::Box(elem));
+                    }
+                }
+            }
+            _ => {}
+        }
+        Err(syn::Error::new_spanned(
+            ty,
+            "expected a reference or a `Box` for the type of a dynamic dispatch arm",
+        ))
+    }
+}
+
+/// This is entry point for handling arguments of `dynamic_dispatch` macro. It accepts the same
+/// syntax as `specialized_dispatch` macro, but dispatches at runtime on a type-erased value by
+/// trying to downcast it to the type of each arm in declaration order.
+#[derive(Debug, Eq, PartialEq)]
+struct DynamicDispatchExpr(SpecializedDispatchExpr);
+
+impl Parse for DynamicDispatchExpr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        // Arms for the same type are allowed, since they are tried in declaration order.
+        let expr = SpecializedDispatchExpr::parse_unchecked(input)?;
+        if let Some(closed) = &expr.closed {
+            return Err(syn::Error::new_spanned(
+                &closed.keyword,
+                "dynamic dispatch doesn't support `closed` option",
+            ));
+        }
+        if let Some(bulk) = &expr.bulk {
+            return Err(syn::Error::new_spanned(
+                &bulk.keyword,
+                "dynamic dispatch doesn't support `bulk` option",
+            ));
+        }
+        // Validation ensures there is at least one default arm.
+        expr.validate()?;
+        if let Some(arm) = expr.arms.iter().filter(|arm| arm.default.is_some()).nth(1) {
+            return Err(syn::Error::new_spanned(
+                arm.default,
+                "dynamic dispatch accepts only one default arm",
+            ));
+        }
+        for arm in &expr.arms {
+            // Downcasts are nested into a single expression, so an arm can't be left out of it.
+            if let Some(attr) = arm.attrs.iter().find(|attr| is_cfg_attr(attr)) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "dynamic dispatch doesn't support `#[cfg]` on arms",
+                ));
+            }
+        }
+        for arm in expr.arms.iter().filter(|arm| arm.default.is_none()) {
+            if let ArmInput::Type(ty) = &arm.input {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "dynamic dispatch requires a value to dispatch on",
+                ));
+            }
+            if let Some(generic_params) = &arm.generic_params {
+                return Err(syn::Error::new_spanned(
+                    generic_params,
+                    "only the default arm can be generic in dynamic dispatch",
+                ));
+            }
+            Downcast::from_arm_type(arm.input.ty())?;
+        }
+        Ok(Self(expr))
+    }
+}
+
+/// Generates a local function for the given arm, which is called after a successful downcast.
+fn generate_arm_function(
+    fn_name: &Ident,
+    dispatch: &SpecializedDispatchExpr,
+    arm: &DispatchArmExpr,
+    body: TokenStream2,
+) -> TokenStream2 {
+    let unsafety = dispatch.unsafety.as_ref();
+    let return_type = &dispatch.to_type;
+    let DispatchArmExpr {
+        attrs,
+        generic_params,
+        input,
+        extra_args,
+        where_clause,
+        ..
+    } = arm;
+    let lifetimes = arm_fn_lifetimes(
+        [input.ty()]
+            .into_iter()
+            .chain(extra_args.iter().map(|arg| &arg.ty))
+            .chain([return_type]),
+        generic_params
+            .iter()
+            .chain(&dispatch.generic_params)
+            .chain(&dispatch.lifted)
+            .flatten(),
+    );
+    let generics = generate_generics(
+        lifetimes,
+        dispatch
+            .lifted
+            .iter()
+            .chain(&dispatch.generic_params)
+            .chain(generic_params)
+            .flatten(),
+    );
+    let input_arg = match input {
+        ArmInput::Value(FnArg { r#mut, name, ty }) => quote! {#r#mut #name: #ty},
+        ArmInput::Type(_) => unreachable!("type-only arms are rejected while parsing"),
+    };
+    let (unsafe_attr, body) = generate_unsafe_body(unsafety, arm, body);
+    quote! {
+        #(#attrs)*
+        #unsafe_attr
+        #unsafety fn #fn_name #generics(#input_arg #(, #extra_args)*) -> #return_type #where_clause {
+            #body
+        }
+    }
+}
+
+impl ToTokens for DynamicDispatchExpr {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let Self(expr) = self;
+        let input = Ident::new("input", Span2::mixed_site());
+        let value = Ident::new("value", Span2::mixed_site());
+        let extra_args: Vec<_> = (0..expr.extra_args.len())
+            .map(|i| Ident::new(&format!("arg{}", i), Span2::mixed_site()))
+            .collect();
+        let extra_exprs = &expr.extra_args;
+        let input_expr = &expr.input_expr;
+        let from_type = &expr.from_type;
+
+        let arm_call = |fn_name: &Ident, value: &Ident, arm: &DispatchArmExpr| {
+            let inferred = arm
+                .generic_params
+                .iter()
+                .flatten()
+                .filter(|param| !matches!(param, GenericParam::Lifetime(_)))
+                .count();
+            let turbofish = generate_turbofish(
+                expr.lifted.iter().chain(&expr.generic_params).flatten(),
+                inferred,
+            );
+            let call = quote_spanned! {arm_call_span(expr.unsafety.as_ref())=>
+                #fn_name #turbofish(#value #(, #extra_args)*)
+            };
+            let call = generate_control_flow(expr, call);
+            if expr.selected_arm {
+                let label = arm_label(arm);
+                quote! { (#call, #label) }
+            } else {
+                call
+            }
+        };
+
+        let mut arm_fns = TokenStream2::new();
+        let mut fallback = TokenStream2::new();
+        let mut downcasts = Vec::new();
+        for (i, arm) in expr.arms.iter().enumerate() {
+            let fn_name = Ident::new(&format!("arm{}", i), Span2::mixed_site());
+            arm_fns.extend(generate_arm_function(
+                &fn_name,
+                expr,
+                arm,
+                generate_arm_body(expr, i, arm, &arm.input.ty().to_token_stream()),
+            ));
+            if arm.default.is_some() {
+                fallback = arm_call(&fn_name, &input, arm);
+            } else {
+                let downcast = Downcast::from_arm_type(arm.input.ty())
+                    .expect("arm types are validated while parsing");
+                downcasts.push((arm_call(&fn_name, &value, arm), downcast));
+            }
+        }
+
+        // Downcasts are nested starting from the last arm, so that they are tried in declaration
+        // order and the default arm is called only if all of them fail.
+        let dispatch = downcasts
+            .into_iter()
+            .rev()
+            .fold(fallback, |fallback, (call, downcast)| match downcast {
+                Downcast::Ref(ty) => quote! {
+                    if let ::core::option::Option::Some(#value) = #input.downcast_ref::<#ty>() {
+                        #call
+                    } else {
+                        #fallback
+                    }
+                },
+                Downcast::Mut(ty) => quote! {
+                    if let ::core::option::Option::Some(#value) = #input.downcast_mut::<#ty>() {
+                        #call
+                    } else {
+                        #fallback
+                    }
+                },
+                Downcast::Box(ty) => quote! {
+                    match #input.downcast::<#ty>() {
+                        ::core::result::Result::Ok(#value) => #call,
+                        ::core::result::Result::Err(#input) => #fallback,
+                    }
+                },
+            });
+
+        let counters = generate_counters(&expr.arms);
+
+        // Arms for the same type are allowed, but only the first one of them is ever selected.
+        let arms: Vec<_> = expr
+            .arms
+            .iter()
+            .filter(|arm| arm.default.is_none())
+            .collect();
+        let shadowed_arm_warnings = generate_shadowed_arm_warnings(&arms, true);
+        let arm_return_warnings = generate_arm_return_warnings(expr);
+
+        tokens.extend(quote! {
+            {
+                #counters
+                #arm_fns
+                #shadowed_arm_warnings
+                #arm_return_warnings
+                let #input: #from_type = #input_expr;
+                #(let #extra_args = #extra_exprs;)*
+                #dispatch
+            }
+        });
+    }
+}
+
+/// This is entry point for handling arguments of `specialize_impl` macro. It parses implementations
+/// of a trait and checks them against the rules of `min_specialization`, so that violations are
+/// reported with explanations. The implementations are expanded as they are.
+struct SpecializeImplExpr(Vec<ItemImpl>);
+
+impl Parse for SpecializeImplExpr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut impls = Vec::new();
+        while !input.is_empty() {
+            impls.push(input.parse()?);
+        }
+        let expr = Self(impls);
+        expr.validate()?;
+        Ok(expr)
+    }
+}
+
+/// Returns the name of the implemented trait, or an error for inherent and negative
+/// implementations.
+fn implemented_trait(item: &ItemImpl) -> Result<String> {
+    match &item.trait_ {
+        Some((None, path, _)) => {
+            let mut name = String::new();
+            write_type_tokens(path.to_token_stream(), &mut name);
+            Ok(name)
+        }
+        _ => Err(syn::Error::new_spanned(
+            &item.self_ty,
+            "expected an implementation of a trait",
+        )),
+    }
+}
+
+/// Returns whether the first implementation covers the self type of the second one, i.e. the
+/// second one specializes the first one if the first one doesn't cover it as well.
+fn impl_covers(item: &ItemImpl, other: &ItemImpl) -> bool {
+    covers_type(&item.generics.params, &item.self_ty, &other.self_ty)
+}
+
+/// Returns the implementation covered by all others, i.e. the most specialized one.
+fn closest_impl<'a>(impls: &[&'a ItemImpl]) -> Option<&'a ItemImpl> {
+    impls
+        .iter()
+        .find(|item| impls.iter().all(|other| impl_covers(other, item)))
+        .copied()
+}
+
+/// Returns the name of an item of an implementation.
+fn impl_item_name(item: &ImplItem) -> Option<&Ident> {
+    match item {
+        ImplItem::Fn(item) => Some(&item.sig.ident),
+        ImplItem::Const(item) => Some(&item.ident),
+        ImplItem::Type(item) => Some(&item.ident),
+        _ => None,
+    }
+}
+
+/// Returns whether an item of an implementation is marked `default`.
+fn impl_item_is_default(item: &ImplItem) -> bool {
+    matches!(item, ImplItem::Fn(item) if item.defaultness.is_some())
+}
+
+/// Counts occurrences of the word in the tokens of a type, including nested groups.
+fn count_word(tokens: &[TypeToken], word: &str) -> usize {
+    tokens
+        .iter()
+        .map(|token| match token {
+            TypeToken::Word(other) => usize::from(other == word),
+            TypeToken::Group(_, inner) => count_word(inner, word),
+            TypeToken::Punct(_) => 0,
+        })
+        .sum()
+}
+
+impl SpecializeImplExpr {
+    /// Checks that implementations are of the same trait and that specializing implementations
+    /// follow the rules of `min_specialization`, which allows only functions to be specialized.
+    /// Overlapping implementations are found by the same syntactic check as arms, e.g.
+    /// `impl<T> Trait for Vec<T>` specializes `impl<T> Trait for T`.
+    ///
+    /// Bounds are ignored by the syntactic check, so only implementations without bounds are known
+    /// to cover others. The rest are left to the compiler, e.g. `impl<T: Foo> Trait for T` doesn't
+    /// overlap with `impl Trait for String` if `String` doesn't implement `Foo`.
+    fn validate(&self) -> Result<()> {
+        let Some(first) = self.0.first() else {
+            return Err(syn::Error::new(
+                Span2::call_site(),
+                "expected at least one implementation",
+            ));
+        };
+        let trait_name = implemented_trait(first)?;
+        for item in self.0.iter().flat_map(|item| &item.items) {
+            let defaultness = match item {
+                ImplItem::Const(item) => item.defaultness,
+                ImplItem::Type(item) => item.defaultness,
+                _ => None,
+            };
+            if let Some(defaultness) = defaultness {
+                return Err(syn::Error::new_spanned(
+                    defaultness,
+                    "only functions can be specialized with `min_specialization`",
+                ));
+            }
+        }
+        for item in &self.0[1..] {
+            if implemented_trait(item)? != trait_name {
+                let (_, path, _) = item.trait_.as_ref().unwrap();
+                return Err(syn::Error::new_spanned(
+                    path,
+                    format!("expected an implementation of `{}`", trait_name),
+                ));
+            }
+        }
+
+        for (i, item) in self.0.iter().enumerate() {
+            if let Some(earlier) = self.0[..i].iter().find(|earlier| {
+                is_unbounded_impl(earlier)
+                    && is_unbounded_impl(item)
+                    && impl_covers(earlier, item)
+                    && impl_covers(item, earlier)
+            }) {
+                let mut name = String::new();
+                write_type_tokens(earlier.self_ty.to_token_stream(), &mut name);
+                return Err(syn::Error::new_spanned(
+                    &item.self_ty,
+                    format!("duplicate implementation for `{}`", name),
+                ));
+            }
+        }
+
+        for item in &self.0 {
+            let parents: Vec<_> = self
+                .0
+                .iter()
+                .filter(|parent| {
+                    is_unbounded_impl(parent)
+                        && impl_covers(parent, item)
+                        && !impl_covers(item, parent)
+                })
+                .collect();
+            if parents.is_empty() {
+                continue;
+            }
+            Self::validate_specializing_impl(item)?;
+            Self::validate_specialized_items(&parents, item)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a specializing implementation doesn't repeat its generic parameters, which isn't
+    /// allowed by `min_specialization`. Its bounds aren't checked, since they are allowed if they
+    /// are implied by the implementation it specializes, or if their traits are marked with
+    /// `#[rustc_specialization_trait]`.
+    fn validate_specializing_impl(item: &ItemImpl) -> Result<()> {
+        let self_ty = type_tokens(item.self_ty.to_token_stream());
+        for name in type_param_names(&item.generics.params) {
+            if count_word(&self_ty, &name) > 1 {
+                return Err(syn::Error::new_spanned(
+                    &item.self_ty,
+                    format!(
+                        "specializing implementations can't repeat parameter `{}` with \
+                         `min_specialization`",
+                        name
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that items of the specializing implementation are marked `default` in the closest
+    /// implementation it specializes. Items inherited by that implementation, including the ones
+    /// provided by the trait, can't be specialized unless it's marked `default` as a whole.
+    fn validate_specialized_items(parents: &[&ItemImpl], item: &ItemImpl) -> Result<()> {
+        // Implementations which don't specialize one another are rejected as conflicting anyway.
+        let Some(parent) = closest_impl(parents) else {
+            return Ok(());
+        };
+        if parent.defaultness.is_some() {
+            return Ok(());
+        }
+        let type_name = |item: &ItemImpl| {
+            let mut name = String::new();
+            write_type_tokens(item.self_ty.to_token_stream(), &mut name);
+            name
+        };
+        for name in item.items.iter().filter_map(impl_item_name) {
+            let parent_item = parent
+                .items
+                .iter()
+                .find(|parent_item| impl_item_name(parent_item) == Some(name));
+            match parent_item {
+                Some(parent_item) if !impl_item_is_default(parent_item) => {
+                    return Err(syn::Error::new_spanned(
+                        impl_item_name(parent_item),
+                        format!(
+                            "`{}` must be marked `default` to be specialized for `{}`",
+                            name,
+                            type_name(item)
+                        ),
+                    ));
+                }
+                None => {
+                    return Err(syn::Error::new_spanned(
+                        name,
+                        format!(
+                            "`{}` must be implemented and marked `default` for `{}` to be \
+                             specialized for `{}`",
+                            name,
+                            type_name(parent),
+                            type_name(item)
+                        ),
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the implementation has no bounds on its generic parameters, which is required
+/// to know that it covers another implementation by comparing their types.
+fn is_unbounded_impl(item: &ItemImpl) -> bool {
+    let bounded = item.generics.params.iter().any(|param| match param {
+        GenericParam::Type(param) => !param.bounds.iter().all(is_maybe_bound),
+        GenericParam::Lifetime(param) => !param.bounds.is_empty(),
+        GenericParam::Const(_) => false,
+    });
+    !bounded && item.generics.where_clause.is_none()
+}
+
+/// Returns whether the bound relaxes a default bound, e.g. `?Sized`, rather than adding one.
+fn is_maybe_bound(bound: &TypeParamBound) -> bool {
+    matches!(bound, TypeParamBound::Trait(bound) if matches!(bound.modifier, TraitBoundModifier::Maybe(_)))
+}
+
+impl ToTokens for SpecializeImplExpr {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(self.0.iter().map(ToTokens::to_token_stream));
+    }
+}
+
+/// This is entry point for handling arguments of `closed_set` macro. It declares a sealed trait
+/// implemented only by the given types, e.g. `pub trait Numeric for u8, u16, f32`, which bounds
+/// the dispatched type of a dispatch with `#[closed(...)]` option naming it.
+struct ClosedSetExpr {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    trait_token: Token![trait],
+    ident: Ident,
+    types: Vec<Type>,
+}
+
+impl Parse for ClosedSetExpr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let trait_token = input.parse()?;
+        let ident = input.parse()?;
+        let for_token = input.parse::<Token![for]>()?;
+        let types = parse_closed_types(input, for_token.span, "closed_set")?;
+        Ok(Self {
+            attrs,
+            vis,
+            trait_token,
+            ident,
+            types,
+        })
+    }
+}
+
+impl ToTokens for ClosedSetExpr {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let Self {
+            attrs,
+            vis,
+            trait_token,
+            ident,
+            types,
+        } = self;
+        // The supertrait is public in a private module, so that it can't be implemented elsewhere.
+        let sealed_mod = format_ident!("__{}_sealed", ident);
+        // Dispatches with `#[closed(...)]` option naming the trait call the hidden method with a
+        // marker type, so that each type of the set is checked to have an arm, see
+        // `generate_closed_check`. References get `'static` lifetimes, which can't be elided there.
+        let marker = Ident::new("M", Span2::mixed_site());
+        let arm_types = types
+            .iter()
+            .map(|ty| with_static_lifetimes(ty.to_token_stream()));
+        tokens.extend(quote! {
+            #(#attrs)*
+            #vis #trait_token #ident: #sealed_mod::Sealed {
+                #[doc(hidden)]
+                fn __closed_set_arms<#marker>()
+                where
+                    #(#marker: ::specialized_dispatch::__private::ClosedSetArm<#arm_types>,)*
+                {
+                }
+            }
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            mod #sealed_mod {
+                pub trait Sealed {}
+            }
+            #(
+                impl #sealed_mod::Sealed for #types {}
+                impl #ident for #types {}
+            )*
+        });
+    }
+}
+
+/// Replaces elided lifetimes of references and `'_` with `'static`, since lifetimes can't be elided
+/// in where clauses. An implementation for any lifetime applies to `'static` as well.
+fn with_static_lifetimes(tokens: TokenStream2) -> TokenStream2 {
+    let mut replaced = TokenStream2::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '&' => {
+                replaced.extend([TokenTree::Punct(punct)]);
+                if !matches!(tokens.peek(), Some(TokenTree::Punct(next)) if next.as_char() == '\'')
+                {
+                    replaced.extend(quote! {'static});
+                }
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => match tokens.next() {
+                Some(TokenTree::Ident(ident)) if ident == "_" => replaced.extend(quote! {'static}),
+                Some(next) => replaced.extend([TokenTree::Punct(punct), next]),
+                None => replaced.extend([TokenTree::Punct(punct)]),
+            },
+            TokenTree::Group(group) => {
+                let mut replaced_group = proc_macro2::Group::new(
+                    group.delimiter(),
+                    with_static_lifetimes(group.stream()),
+                );
+                replaced_group.set_span(group.span());
+                replaced.extend([TokenTree::Group(replaced_group)]);
+            }
+            token => replaced.extend([token]),
+        }
+    }
+    replaced
+}
+
+/// Generates the implementation of `SpecializedDispatch` trait for a struct with a single field.
+fn generate_newtype_implementation(input: &DeriveInput) -> Result<TokenStream2> {
+    let field = match &input.data {
+        Data::Struct(data) if data.fields.len() == 1 => data.fields.iter().next().unwrap(),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`SpecializedDispatch` can only be derived for structs with a single field",
+            ))
+        }
+    };
+    let name = &input.ident;
+    let inner_type = &field.ty;
+    let member = field
+        .ident
+        .clone()
+        .map_or_else(|| Member::Unnamed(Index::from(0)), Member::Named);
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::specialized_dispatch::SpecializedDispatch for #name #type_generics
+        #where_clause
+        {
+            type Inner = #inner_type;
+
+            fn into_inner(self) -> #inner_type {
+                self.#member
+            }
+        }
+    })
+}
+
+/// Returns an error explaining the required toolchain if the compiler doesn't accept unstable
+/// features, which are required for specialization.
+fn toolchain_error() -> Option<TokenStream> {
+    cfg!(stable_toolchain).then(|| {
+        syn::Error::new(
+            Span2::call_site(),
+            "specialized-dispatch requires a nightly toolchain, since it relies on \
+             `min_specialization` feature; build with `cargo +nightly` and add \
+             `#![feature(min_specialization)]` to the crate attributes",
+        )
+        .to_compile_error()
+        .into()
+    })
+}
+
+/// Returns the root source file of the crate being compiled, which is the only source file given
+/// to rustc, or `None` if it isn't known. The macro runs within the compiler, so its arguments are
+/// those of the compiler. Under rustdoc, spans of doctests point at the documented file instead.
+fn crate_root_file() -> Option<std::path::PathBuf> {
+    let mut args = std::env::args_os();
+    let program = std::path::PathBuf::from(args.next()?);
+    if program.file_stem().is_some_and(|stem| stem == "rustdoc") {
+        return None;
+    }
+    let mut sources =
+        args.filter(|arg| std::path::Path::new(arg).extension() == Some("rs".as_ref()));
+    let root = sources.next()?;
+    if sources.next().is_some() {
+        return None;
+    }
+    std::fs::canonicalize(root).ok()
+}
+
+/// Returns whether the crate attributes enable specialization, if the call of `check_setup` is in
+/// the root file of the crate. Otherwise, or if the file can't be read or parsed, returns `None`.
+fn enables_specialization(span: Span2) -> Option<bool> {
+    let path = std::fs::canonicalize(span.unwrap().local_file()?).ok()?;
+    if path != crate_root_file()? {
+        return None;
+    }
+    let file = syn::parse_file(&std::fs::read_to_string(path).ok()?).ok()?;
+    let features = ["min_specialization", "specialization"].map(|name| Ident::new(name, span));
+    Some(file.attrs.iter().any(|attr| {
+        features
+            .iter()
+            .any(|feature| contains_ident(attr.meta.to_token_stream(), feature))
+    }))
+}
+
+/// Entry point for the macro. Please see [the crate documentation][docs] for more information and
+/// example.
+///
+/// [docs]: https://docs.rs/specialized-dispatch
+#[proc_macro]
+pub fn specialized_dispatch(input: TokenStream) -> TokenStream {
+    if let Some(error) = toolchain_error() {
+        return error;
+    }
+    parse_macro_input!(input as SpecializedDispatchExpr)
+        .into_token_stream()
+        .into()
+}
+
+/// Alternative entry point for the macro with a `match`-like syntax, where the expression being
+/// dispatched comes first. Please see [the crate documentation][docs] for more information and
+/// example.
+///
+/// [docs]: https://docs.rs/specialized-dispatch
+#[proc_macro]
+pub fn specialized_match(input: TokenStream) -> TokenStream {
+    if let Some(error) = toolchain_error() {
+        return error;
+    }
+    parse_macro_input!(input with parse_specialized_match)
+        .into_token_stream()
+        .into()
+}
+
+/// Runtime counterpart of the macro, which dispatches a type-erased value such as `&dyn Any` or
+/// `Box<dyn Any>` by downcasting it to the type of each arm in declaration order. Please see [the
+/// crate documentation][docs] for more information and example.
+///
+/// [docs]: https://docs.rs/specialized-dispatch
+#[proc_macro]
+pub fn dynamic_dispatch(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as DynamicDispatchExpr)
+        .into_token_stream()
+        .into()
+}
+
+/// Item-level counterpart of the macro, which expands to the given implementations of a trait
+/// after checking them against the rules of `min_specialization`. Please see [the crate
+/// documentation][docs] for more information and example.
+///
+/// [docs]: https://docs.rs/specialized-dispatch
+#[proc_macro]
+pub fn specialize_impl(input: TokenStream) -> TokenStream {
+    if let Some(error) = toolchain_error() {
+        return error;
+    }
+    parse_macro_input!(input as SpecializeImplExpr)
+        .into_token_stream()
+        .into()
+}
+
+/// Declares a sealed trait implemented only by the given types, e.g.
+/// `closed_set!(pub trait Numeric for u8, u16, f32)`. Dispatches with `#[closed(Numeric)]` option
+/// naming the trait can't be instantiated with other types, and need an arm for each of them unless
+/// there is a default arm. Please see [the crate documentation][docs] for more information and
+/// example.
+///
+/// [docs]: https://docs.rs/specialized-dispatch
+#[proc_macro]
+pub fn closed_set(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as ClosedSetExpr)
+        .into_token_stream()
+        .into()
+}
+
+/// Derives `SpecializedDispatch` for newtype wrappers, i.e. structs with a single field, so that
+/// they are dispatched as their inner type unless there is an arm for the wrapper itself. Please
+/// see [the crate documentation][docs] for more information and example.
+///
+/// [docs]: https://docs.rs/specialized-dispatch
+#[proc_macro_derive(SpecializedDispatch)]
+pub fn derive_specialized_dispatch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    generate_newtype_implementation(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Checks whether the crate is set up for specialization, which is required by all macros except
+/// `dynamic_dispatch`. Please see [the crate documentation][docs] for more information.
+///
+/// This is meant to be called once at the crate root. It fails with an error explaining the
+/// required toolchain if unstable features aren't available. Otherwise, if it's called from the
+/// root file of the crate and the feature isn't enabled by its attributes, it fails with an error
+/// naming the attribute to add. Elsewhere, or if the file can't be read, the feature is probed by
+/// implementations which conflict without it, where the error names a trait after the attribute to
+/// add, i.e. `add_feature_min_specialization_to_crate_attributes`.
+///
+/// [docs]: https://docs.rs/specialized-dispatch
+#[proc_macro]
+pub fn check_setup(input: TokenStream) -> TokenStream {
+    let input = TokenStream2::from(input);
+    if !input.is_empty() {
+        return syn::Error::new_spanned(input, "expected no arguments")
+            .to_compile_error()
+            .into();
+    }
+    if let Some(error) = toolchain_error() {
+        return error;
+    }
+    if enables_specialization(Span2::call_site()) == Some(false) {
+        return syn::Error::new(
+            Span2::call_site(),
+            "`min_specialization` feature isn't enabled; add `#![feature(min_specialization)]` \
+             to the crate attributes, which requires a nightly toolchain",
+        )
+        .to_compile_error()
+        .into();
+    }
+    // Without the feature, `default` is rejected and the implementations below conflict. rustc
+    // then names the trait in its error, which tells what is missing.
+    let probe = Ident::new(
+        "add_feature_min_specialization_to_crate_attributes",
+        Span2::call_site(),
+    );
+    quote! {
+        const _: () = {
+            #[allow(non_camel_case_types, dead_code)]
+            trait #probe {
+                fn probe();
+            }
+            impl<T> #probe for T {
+                default fn probe() {}
+            }
+            impl #probe for () {
+                fn probe() {}
+            }
+        };
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+    use syn::{parse::Parser, visit_mut::VisitMut};
+
+    #[test]
+    fn parse_arm_with_concrete_type() {
+        let arm: DispatchArmExpr = parse_quote!(fn (v: u8) => format!("u8: {}", v));
+        assert_eq!(
+            arm,
+            DispatchArmExpr {
+                attrs: vec![],
+                default: None,
+                unsafety: None,
+                label: None,
+                generic_params: None,
+                input: ArmInput::Value(parse_quote!(v: u8)),
+                extra_args: vec![],
+                where_clause: None,
+                body: parse_quote!(format!("u8: {}", v)),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_arm_with_generic_type() {
+        let arm: DispatchArmExpr = parse_quote!(default fn <T>(_: T) => format!("default value"));
+        assert_eq!(
+            arm,
+            DispatchArmExpr {
+                attrs: vec![],
+                default: Some(Default::default()),
+                unsafety: None,
+                label: None,
+                generic_params: Some(parse_quote!(T)),
+                input: ArmInput::Value(parse_quote!(_: T)),
+                extra_args: vec![],
+                where_clause: None,
+                body: parse_quote!(format!("default value")),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_specialized_dispatch_expr() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn (v: u8) => format!("u8: {}", v),
+            fn (v: u16) => format!("u16: {}", v),
+            expr,
+        };
+        assert_eq!(
+            expr,
+            SpecializedDispatchExpr {
+                selected_arm: false,
+                try_arms: false,
+                control_flow: false,
+                unsafety: None,
+                generic_params: None,
+                lifted: None,
+                closed: None,
+                bulk: None,
+                from_type: parse_quote!(E),
+                to_type: parse_quote!(String),
+                arms: vec![
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: Some(Default::default()),
+                        unsafety: None,
+                        label: None,
+                        generic_params: Some(parse_quote!(T)),
+                        input: ArmInput::Value(parse_quote!(_: T)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!(format!("default value")),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: None,
+                        input: ArmInput::Value(parse_quote!(v: u8)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!(format!("u8: {}", v)),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: None,
+                        input: ArmInput::Value(parse_quote!(v: u16)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!(format!("u16: {}", v)),
+                    },
+                ],
+                input_expr: Some(parse_quote!(expr)),
+                extra_args: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_trailing_args() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            E -> String,
+            default fn <T>(_: T, arg1: u8, arg2: u16, arg3: &str) => format!("default value"),
+            fn (v: u8, arg1: u8, arg2: u16, arg3: &str) => format!("u8: {}", v),
+            fn (v: u16, arg1: u8, arg2: u16, arg3: &str) => format!("u16: {}", v),
+            expr,
+            1u8,
+            2u16,
+            "bugun_bayram_erken_kalkin_cocuklar",
+        };
+
+        assert_eq!(
+            expr,
+            SpecializedDispatchExpr {
+                selected_arm: false,
+                try_arms: false,
+                control_flow: false,
+                unsafety: None,
+                generic_params: None,
+                lifted: None,
+                closed: None,
+                bulk: None,
+                from_type: parse_quote!(E),
+                to_type: parse_quote!(String),
+                arms: vec![
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: Some(Default::default()),
+                        unsafety: None,
+                        label: None,
+                        generic_params: Some(parse_quote!(T)),
+                        input: ArmInput::Value(parse_quote!(_: T)),
+                        extra_args: vec![
+                            parse_quote!(arg1: u8),
+                            parse_quote!(arg2: u16),
+                            parse_quote!(arg3: &str)
+                        ],
+                        where_clause: None,
+                        body: parse_quote!(format!("default value")),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: None,
+                        input: ArmInput::Value(parse_quote!(v: u8)),
+                        extra_args: vec![
+                            parse_quote!(arg1: u8),
+                            parse_quote!(arg2: u16),
+                            parse_quote!(arg3: &str)
+                        ],
+                        where_clause: None,
+                        body: parse_quote!(format!("u8: {}", v)),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: None,
+                        input: ArmInput::Value(parse_quote!(v: u16)),
+                        extra_args: vec![
+                            parse_quote!(arg1: u8),
+                            parse_quote!(arg2: u16),
+                            parse_quote!(arg3: &str)
+                        ],
+                        where_clause: None,
+                        body: parse_quote!(format!("u16: {}", v)),
+                    },
+                ],
+                input_expr: Some(parse_quote!(expr)),
+                extra_args: vec![
+                    parse_quote!(1u8),
+                    parse_quote!(2u16),
+                    parse_quote!("bugun_bayram_erken_kalkin_cocuklar")
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_mut_arg() {
+        let arg: FnArg = parse_quote!(mut v: u8);
+        assert_eq!(
+            arg,
+            FnArg {
+                r#mut: Some(parse_quote!(mut)),
+                ty: parse_quote!(u8),
+                name: FnArgName::Ident(parse_quote!(v)),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_specialized_match_expr() {
+        let tpl = Ident::new("T", Span2::mixed_site());
+        let expr = parse_specialized_match
+            .parse2(quote! {
+                expr: E -> String {
+                    v: u8 => format!("u8: {}", v),
+                    mut v: u16 => { v += 1; format!("u16: {}", v) }
+                    _ => format!("default value"),
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            expr,
+            SpecializedDispatchExpr {
+                selected_arm: false,
+                try_arms: false,
+                control_flow: false,
+                unsafety: None,
+                generic_params: None,
+                lifted: None,
+                closed: None,
+                bulk: None,
+                from_type: parse_quote!(E),
+                to_type: parse_quote!(String),
+                arms: vec![
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: None,
+                        input: ArmInput::Value(parse_quote!(v: u8)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!(format!("u8: {}", v)),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: None,
+                        input: ArmInput::Value(parse_quote!(mut v: u16)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!({
+                            v += 1;
+                            format!("u16: {}", v)
+                        }),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: Some(Default::default()),
+                        unsafety: None,
+                        label: None,
+                        generic_params: Some(parse_quote!(#tpl)),
+                        input: ArmInput::Value(parse_quote!(_: #tpl)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!(format!("default value")),
+                    },
+                ],
+                input_expr: Some(parse_quote!(expr)),
+                extra_args: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_type_only_dispatch_expr() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            E -> &'static str,
+            default fn <T: Debug> => "default",
+            fn <u8> => "u8",
+            fn <const N: usize> <[u8; N]> => "array",
+        };
+        assert_eq!(
+            expr,
+            SpecializedDispatchExpr {
+                selected_arm: false,
+                try_arms: false,
+                control_flow: false,
+                unsafety: None,
+                generic_params: None,
+                lifted: None,
+                closed: None,
+                bulk: None,
+                from_type: parse_quote!(E),
+                to_type: parse_quote!(&'static str),
+                arms: vec![
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: Some(Default::default()),
+                        unsafety: None,
+                        label: None,
+                        generic_params: Some(parse_quote!(T: Debug)),
+                        input: ArmInput::Type(parse_quote!(T)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!("default"),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: None,
+                        input: ArmInput::Type(parse_quote!(u8)),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!("u8"),
+                    },
+                    DispatchArmExpr {
+                        attrs: vec![],
+                        default: None,
+                        unsafety: None,
+                        label: None,
+                        generic_params: Some(parse_quote!(const N: usize)),
+                        input: ArmInput::Type(parse_quote!([u8; N])),
+                        extra_args: vec![],
+                        where_clause: None,
+                        body: parse_quote!("array"),
+                    },
+                ],
+                input_expr: None,
+                extra_args: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_mixed_type_only_and_value_arms() {
+        let result = syn::parse2::<SpecializedDispatchExpr>(quote! {
+            E -> &'static str,
+            default fn <T> => "default",
+            fn (_: u8) => "u8",
+            expr,
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "type-only arms cannot be mixed with arms taking arguments"
+        );
+    }
+
+    #[test]
+    fn parse_arm_with_const_generic_type() {
+        let arm: DispatchArmExpr =
+            parse_quote!(default fn <const N: usize>(v: [u8; N]) => format!("{:?}", v));
+        assert_eq!(
+            arm,
+            DispatchArmExpr {
+                attrs: vec![],
+                default: Some(Default::default()),
+                unsafety: None,
+                label: None,
+                generic_params: Some(parse_quote!(const N: usize)),
+                input: ArmInput::Value(parse_quote!(v: [u8; N])),
+                extra_args: vec![],
+                where_clause: None,
+                body: parse_quote!(format!("{:?}", v)),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_dynamic_dispatch_errors() {
+        let error = |tokens| {
+            syn::parse2::<DynamicDispatchExpr>(tokens)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(quote! {
+                &dyn Any -> String,
+                fn (v: &u8) => format!("u8: {}", v),
+                expr,
+            }),
+            "expected a default arm"
+        );
+        assert_eq!(
+            error(quote! {
+                &dyn Any -> String,
+                fn (v: u8) => format!("u8: {}", v),
+                default fn <T>(_: T) => format!("default value"),
+                expr,
+            }),
+            "expected a reference or a `Box` for the type of a dynamic dispatch arm"
+        );
+        assert_eq!(
+            error(quote! {
+                &dyn Any -> String,
+                fn <T>(v: &Vec<T>) => format!("Vec<T>: {}", v.len()),
+                default fn <T>(_: T) => format!("default value"),
+                expr,
+            }),
+            "only the default arm can be generic in dynamic dispatch"
+        );
+    }
+
+    #[test]
+    fn parse_selected_arm_with_labels() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            #[selected_arm]
+            E -> String,
+            default fn fallback <T>(_: T) => format!("default value"),
+            fn fast_path(v: u8) => format!("u8: {}", v),
+            expr,
+        };
+        assert!(expr.selected_arm);
+        assert_eq!(expr.arms[0].label, Some(parse_quote!(fallback)));
+        assert_eq!(expr.arms[0].generic_params, Some(parse_quote!(T)));
+        assert_eq!(expr.arms[1].label, Some(parse_quote!(fast_path)));
+        assert_eq!(expr.arms[1].generic_params, None);
+
+        let result = syn::parse2::<SpecializedDispatchExpr>(quote! {
+            #[unknown]
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            expr,
+        });
+        assert_eq!(result.unwrap_err().to_string(), "unknown dispatch option");
+    }
+
+    #[test]
+    fn parse_lifted_generics() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            #[lift('a, S: Serializer, const N: usize)]
+            E -> Result<[u8; N], S::Error>,
+            default fn <T>(_: T) => unimplemented!(),
+            expr,
+        };
+        assert_eq!(
+            expr.lifted,
+            Some(parse_quote!('a, S: Serializer, const N: usize))
+        );
+        assert_eq!(
+            generate_trait_path(&parse_quote!(Trait), &expr).to_string(),
+            quote!(Trait<'a, S, N>).to_string()
+        );
+
+        let result = syn::parse2::<SpecializedDispatchExpr>(quote! {
+            #[lift()]
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            expr,
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "expected at least one generic parameter in `lift`"
+        );
+    }
+
+    #[test]
+    fn parse_control_flow() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            #[control_flow]
+            E -> ControlFlow<Option<u8>, u8>,
+            default fn <T>(_: T) => ControlFlow::Break(None),
+            expr,
+        };
+        assert!(expr.control_flow);
+        assert!(!expr.try_arms);
+
+        let result = syn::parse2::<SpecializedDispatchExpr>(quote! {
+            #[control_flow]
+            E -> Option<u8>,
+            default fn <T>(_: T) => None,
+            expr,
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "expected a `ControlFlow` return type for `control_flow`"
+        );
+    }
+
+    #[test]
+    fn arm_returns() {
+        let returns = |body: Expr| {
+            let mut returns = ArmReturns::default();
+            returns.visit_expr(&body);
+            returns.0.len()
+        };
+        assert_eq!(returns(parse_quote!(return 5)), 1);
+        assert_eq!(
+            returns(parse_quote!({
+                if v {
+                    return 5;
+                }
+                return 6;
+            })),
+            2
+        );
+        assert_eq!(returns(parse_quote!(v.map(|x| return x))), 0);
+        assert_eq!(returns(parse_quote!(async { return 5 })), 0);
+        assert_eq!(
+            returns(parse_quote!({
+                fn f() -> u8 {
+                    return 5;
+                }
+                f()
+            })),
+            0
+        );
+    }
+
+    #[test]
+    fn derive_newtype() {
+        let derive = |input: DeriveInput| generate_newtype_implementation(&input);
+        let expected = quote! {
+            impl<T> ::specialized_dispatch::SpecializedDispatch for Id<T> {
+                type Inner = T;
+
+                fn into_inner(self) -> T {
+                    self.0
+                }
+            }
+        };
+        assert_eq!(
+            derive(parse_quote!(
+                struct Id<T>(T);
+            ))
+            .unwrap()
+            .to_string(),
+            expected.to_string()
+        );
+        let expected = quote! {
+            impl<T: Ord> ::specialized_dispatch::SpecializedDispatch for Sorted<T> {
+                type Inner = Vec<T>;
+
+                fn into_inner(self) -> Vec<T> {
+                    self.items
+                }
+            }
+        };
+        assert_eq!(
+            derive(parse_quote!(
+                struct Sorted<T: Ord> {
+                    items: Vec<T>,
+                }
+            ))
+            .unwrap()
+            .to_string(),
+            expected.to_string()
+        );
+        for input in [
+            parse_quote!(
+                struct Pair(u8, u8);
+            ),
+            parse_quote!(
+                struct Unit;
+            ),
+            parse_quote!(
+                enum Id {
+                    A(u8),
+                }
+            ),
+        ] {
+            assert_eq!(
+                derive(input).unwrap_err().to_string(),
+                "`SpecializedDispatch` can only be derived for structs with a single field"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_specialize_impl() {
+        let error = |input: TokenStream2| {
+            syn::parse2::<SpecializeImplExpr>(input)
+                .err()
+                .map(|error| error.to_string())
+        };
+        assert_eq!(
+            error(quote! {
+                impl<T> Tr for T {
+                    default fn f(&self) {}
+                    default fn g(&self) {}
+                }
+                impl<T> Tr for Vec<T> {
+                    fn f(&self) {}
+                    default fn g(&self) {}
+                }
+                impl Tr for Vec<u8> {
+                    fn g(&self) {}
+                }
+                impl<const N: usize> Tr for [u8; N] {}
+            }),
+            None
+        );
+        assert_eq!(
+            error(quote! {
+                impl<T> Tr for T {
+                    default fn f(&self) {}
+                }
+                impl<T> Tr for Vec<T> {}
+                impl Tr for Vec<u8> {
+                    fn f(&self) {}
+                }
+            })
+            .unwrap(),
+            "`f` must be implemented and marked `default` for `Vec<T>` to be specialized for \
+             `Vec<u8>`"
+        );
+        assert_eq!(
+            error(quote! {
+                default impl<T> Tr for T {}
+                impl Tr for u8 {
+                    fn f(&self) {}
+                }
+            }),
+            None
+        );
+        assert_eq!(
+            error(quote! {
+                impl<T> Tr for T {
+                    default fn f(&self) {}
+                }
+                impl<T> Tr for Vec<T> {
+                    fn f(&self) {}
+                }
+                impl Tr for Vec<u8> {
+                    fn f(&self) {}
+                }
+            })
+            .unwrap(),
+            "`f` must be marked `default` to be specialized for `Vec<u8>`"
+        );
+        assert_eq!(
+            error(quote! {
+                impl<T> Tr for T {}
+                impl Tr for u8 {
+                    fn f(&self) {}
+                }
+            })
+            .unwrap(),
+            "`f` must be implemented and marked `default` for `T` to be specialized for `u8`"
+        );
+        assert_eq!(
+            error(quote! {
+                impl<T> Tr for T {
+                    default const N: usize = 0;
+                }
+            })
+            .unwrap(),
+            "only functions can be specialized with `min_specialization`"
+        );
+        assert_eq!(
+            error(quote! {
+                impl<T: Foo, U> Tr for (T, U) {
+                    default fn f(&self) {}
+                }
+                impl<T: Foo> Tr for (T, u16) {
+                    fn f(&self) {}
+                }
+                impl<T> Tr for Vec<T> where T: Foo {}
+            }),
+            None
+        );
+        assert_eq!(
+            error(quote! {
+                impl<T: Foo> Tr for T {
+                    fn f(&self) {}
+                }
+                impl Tr for String {
+                    fn f(&self) {}
+                }
+            }),
+            None
+        );
+        assert_eq!(
+            error(quote! {}).unwrap(),
+            "expected at least one implementation"
+        );
+    }
+
+    #[test]
+    fn parse_bulk() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            #[bulk]
+            &mut [E] -> usize,
+            default fn <T>(v: &mut [T]) => v.len(),
+            fn (v: &mut [u8]) => v.len(),
+            values,
+        };
+        assert_eq!(expr.from_type, parse_quote!(E));
+        assert_eq!(expr.arms[0].input.ty(), &parse_quote!(T));
+        assert_eq!(expr.arms[1].input.ty(), &parse_quote!(u8));
+        assert!(matches!(
+            expr.bulk.unwrap().container,
+            BulkContainer::Slice(Some(_))
+        ));
+
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            #[bulk]
+            Vec<E> -> usize,
+            default fn <T>(v: Vec<T>) => v.len(),
+            fn (v: Vec<u8>) => v.len(),
+            values,
+        };
+        assert_eq!(expr.from_type, parse_quote!(E));
+        assert_eq!(expr.arms[1].input.ty(), &parse_quote!(u8));
+        assert_eq!(
+            expr.bulk.unwrap().slice_of(quote! {Self}).to_string(),
+            quote! {Vec<Self>}.to_string()
+        );
+
+        let result = syn::parse2::<SpecializedDispatchExpr>(quote! {
+            #[bulk]
+            E -> usize,
+            default fn <T> => 0,
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "expected a slice such as `&[T]` for `bulk`"
+        );
+    }
+
+    #[test]
+    fn parse_closed_set() {
+        let expr: ClosedSetExpr = parse_quote! {
+            /// Numeric types.
+            pub trait Numeric for u8, u16, f32,
+        };
+        assert_eq!(expr.attrs.len(), 1);
+        assert_eq!(expr.ident, "Numeric");
+        assert_eq!(expr.types.len(), 3);
+
+        let result = syn::parse2::<ClosedSetExpr>(quote! {trait Numeric for});
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "expected at least one type in `closed_set`"
+        );
+
+        let static_lifetimes = |ty: Type| with_static_lifetimes(ty.to_token_stream()).to_string();
+        assert_eq!(
+            static_lifetimes(parse_quote!(Vec<(&str, &mut Formatter<'_>, &'a u8)>)),
+            quote!(Vec<(&'static str, &'static mut Formatter<'static>, &'a u8)>).to_string()
+        );
+    }
+
+    #[test]
+    fn type_names() {
+        let is_result = |ty: Type| is_type_named(&ty, "Result");
+        assert!(is_result(parse_quote!(Result<u8, Error>)));
+        assert!(is_result(parse_quote!(io::Result<u8>)));
+        assert!(is_result(parse_quote!(::core::result::Result<u8, Error>)));
+        assert!(!is_result(parse_quote!(Option<u8>)));
+        assert!(!is_result(parse_quote!((Result<u8, Error>, &'static str))));
+        assert!(is_type_named(
+            &parse_quote!(std::ops::ControlFlow<u8, u16>),
+            "ControlFlow"
+        ));
+    }
+
+    #[test]
+    fn arm_label_from_type() {
+        let label = |arm: DispatchArmExpr| arm_label(&arm);
+        assert_eq!(label(parse_quote!(fn (v: u8) => v)), "u8");
+        assert_eq!(
+            label(parse_quote!(fn (v: &'a mut [u8; 4]) => v)),
+            "&'a mut [u8; 4]"
+        );
+        assert_eq!(
+            label(parse_quote!(fn (v: HashMap<String, Vec<u8>>) => v)),
+            "HashMap<String, Vec<u8>>"
+        );
+        assert_eq!(
+            label(parse_quote!(fn (v: Box<dyn Any + Send>) => v)),
+            "Box<dyn Any + Send>"
+        );
+        assert_eq!(label(parse_quote!(fn label(v: u8) => v)), "label");
+    }
+
+    #[test]
+    fn parse_arm_with_where_clause() {
+        let arm: DispatchArmExpr = parse_quote! {
+            default fn <T>(v: T) where T: IntoIterator, T::Item: Debug => format!("{:?}", v)
+        };
+        assert_eq!(
+            arm.where_clause,
+            Some(parse_quote!(where T: IntoIterator, T::Item: Debug))
+        );
+        let arm: DispatchArmExpr = parse_quote!(default fn <T> where T: Debug => "default");
+        assert_eq!(arm.input, ArmInput::Type(parse_quote!(T)));
+        assert_eq!(arm.where_clause, Some(parse_quote!(where T: Debug)));
+        let expr = parse_specialized_match
+            .parse2(quote! {
+                expr: E -> String {
+                    default <T> v: T where T: Debug => format!("{:?}", v),
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            expr.arms[0].where_clause,
+            Some(parse_quote!(where T: Debug))
+        );
+    }
+
+    #[test]
+    fn parse_arm_with_attributes() {
+        let arm: DispatchArmExpr = parse_quote! {
+            #[cold]
+            #[inline(never)]
+            default fn <T>(_: T) => "default"
+        };
+        assert_eq!(
+            arm.attrs,
+            vec![parse_quote!(#[cold]), parse_quote!(#[inline(never)])]
+        );
+        assert!(arm.default.is_some());
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            E -> &'static str,
+            default fn <T>(_: T) => "default",
+            #[cfg(unix)]
+            fn (_: u8) => "unix",
+            #[cfg(not(unix))]
+            fn (_: u8) => "not unix",
+            expr,
+        };
+        assert_eq!(expr.arms.len(), 3);
+        assert_eq!(expr.arms[2].attrs, vec![parse_quote!(#[cfg(not(unix))])]);
+        let expr = parse_specialized_match
+            .parse2(quote! {
+                expr: E -> &'static str {
+                    #[inline(always)]
+                    v: u8 => "u8",
+                    _ => "default",
+                }
+            })
+            .unwrap();
+        assert_eq!(expr.arms[0].attrs, vec![parse_quote!(#[inline(always)])]);
+    }
+
+    #[test]
+    fn parse_unsafe_dispatch() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            unsafe E -> u8,
+            default fn <T>(_: T) => 0,
+            unsafe fn (v: *const u8) => *v,
+            expr,
+        };
+        assert!(expr.unsafety.is_some());
+        assert!(expr.arms[0].unsafety.is_none());
+        assert!(expr.arms[1].unsafety.is_some());
+        // The type of an unsafe function pointer isn't an unsafe dispatch.
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            unsafe fn(u8) -> u8 -> u8,
+            default fn <T>(_: T) => 0,
+            expr,
+        };
+        assert!(expr.unsafety.is_none());
+        assert_eq!(expr.from_type, parse_quote!(unsafe fn(u8) -> u8));
+        let expr = parse_specialized_match
+            .parse2(quote! {
+                unsafe expr: E -> u8 {
+                    unsafe v: *const u8 => *v,
+                    _ => 0,
+                }
+            })
+            .unwrap();
+        assert!(expr.unsafety.is_some());
+        assert!(expr.arms[0].unsafety.is_some());
+        let expr = parse_specialized_match
+            .parse2(quote! {
+                unsafe { expr }: E -> u8 {
+                    _ => 0,
+                }
+            })
+            .unwrap();
+        assert!(expr.unsafety.is_none());
+        assert!(matches!(expr.input_expr, Some(Expr::Unsafe(_))));
+    }
+
+    #[test]
+    fn collect_arm_fn_lifetimes() {
+        let types: Vec<Type> = vec![
+            parse_quote!(&'a mut Vec<&'b str>),
+            parse_quote!(&'static str),
+            parse_quote!(Formatter<'_>),
+            parse_quote!((&'a str, Cow<'c, [u8]>)),
+        ];
+        let lifetimes = arm_fn_lifetimes(&types, []);
+        assert_eq!(
+            lifetimes,
+            vec![parse_quote!('a), parse_quote!('b), parse_quote!('c)]
+        );
+        let generic_params: Punctuated<GenericParam, Token![,]> = parse_quote!('b, T);
+        let lifetimes = arm_fn_lifetimes(&types, &generic_params);
+        assert_eq!(lifetimes, vec![parse_quote!('a), parse_quote!('c)]);
+
+        let types: Vec<Type> = vec![
+            parse_quote!(&'b dyn for<'a> Fn(&'a str) -> &'a str),
+            parse_quote!(for<'a> fn(&'a u8, &'c u8)),
+            parse_quote!(Box<dyn for<'a> Fn(&'a str) + 'a>),
+        ];
+        let lifetimes = arm_fn_lifetimes(&types, []);
+        assert_eq!(
+            lifetimes,
+            vec![parse_quote!('b), parse_quote!('c), parse_quote!('a)]
+        );
+    }
+
+    #[test]
+    fn parse_dispatch_generic_params() {
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            <W: Write, const N: usize> E -> [u8; N],
+            default fn <T>(_: T, _: &mut W) => [0; N],
+            expr,
+            writer,
+        };
+        assert_eq!(
+            expr.generic_params,
+            Some(parse_quote!(W: Write, const N: usize))
+        );
+        assert_eq!(expr.from_type, parse_quote!(E));
+        // Qualified paths are dispatched types rather than generic parameters.
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            <E as IntoIterator>::Item -> String,
+            default fn <T>(_: T) => String::new(),
+            expr,
+        };
+        assert_eq!(expr.generic_params, None);
+        assert_eq!(expr.from_type, parse_quote!(<E as IntoIterator>::Item));
+        let expr: SpecializedDispatchExpr = parse_quote! {
+            <E>::Item -> String,
+            default fn <T>(_: T) => String::new(),
+            expr,
+        };
+        assert_eq!(expr.generic_params, None);
+        assert_eq!(expr.from_type, parse_quote!(<E>::Item));
+    }
+
+    #[test]
+    fn pattern_covers_types() {
+        let covers = |params: Punctuated<GenericParam, Token![,]>, pattern: Type, ty: Type| {
+            covers_type(&params, &pattern, &ty)
+        };
+        assert!(covers(parse_quote!(), parse_quote!(u8), parse_quote!(u8)));
+        assert!(!covers(parse_quote!(), parse_quote!(u8), parse_quote!(u16)));
+        assert!(covers(
+            parse_quote!(T),
+            parse_quote!(Vec<T>),
+            parse_quote!(Vec<Vec<u8>>)
+        ));
+        assert!(!covers(
+            parse_quote!(T),
+            parse_quote!(Vec<T>),
+            parse_quote!(Option<u8>)
+        ));
+        assert!(covers(
+            parse_quote!('a, T),
+            parse_quote!(&'a [T]),
+            parse_quote!(&[u8])
+        ));
+        assert!(covers(
+            parse_quote!(const N: usize),
+            parse_quote!([u8; N]),
+            parse_quote!([u8; 4])
+        ));
+    }
+
+    #[test]
+    fn normalize_type_names() {
+        let name = |ty: Type| normalized_type_name(&ty);
+        assert_eq!(name(parse_quote!(u8)), "u8");
+        assert_eq!(name(parse_quote!(core::primitive::u8)), "u8");
+        assert_eq!(name(parse_quote!(::std::primitive::u8)), "u8");
+        assert_eq!(
+            name(parse_quote!(&std::vec::Vec<::alloc::string::String>)),
+            "&Vec<String>"
+        );
+        assert_eq!(
+            name(parse_quote!(my::core::primitive::u8)),
+            "my::core::primitive::u8"
+        );
+        assert_eq!(
+            name(parse_quote!(mycore::option::Option<u8>)),
+            "mycore::option::Option<u8>"
+        );
+        // Only the paths of the types themselves are normalized.
+        assert_eq!(
+            name(parse_quote!(std::option::IntoIter<u8>)),
+            "std::option::IntoIter<u8>"
+        );
+        assert_eq!(
+            name(parse_quote!(std::vec::IntoIter<u8>)),
+            "std::vec::IntoIter<u8>"
+        );
+        assert_eq!(
+            name(parse_quote!(core::primitive::u8x)),
+            "core::primitive::u8x"
+        );
+    }
+
+    /// Expands calls of the macros in place. The block of an expansion in tail position is
+    /// flattened into the enclosing block, so that snapshots read like handwritten code.
+    struct ExpandMacros;
+
+    impl VisitMut for ExpandMacros {
+        fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+            if let syn::Stmt::Macro(stmt_macro) = stmt {
+                *stmt = syn::Stmt::Expr(
+                    Expr::Macro(syn::ExprMacro {
+                        attrs: stmt_macro.attrs.clone(),
+                        mac: stmt_macro.mac.clone(),
+                    }),
+                    stmt_macro.semi_token,
+                );
+            }
+            syn::visit_mut::visit_stmt_mut(self, stmt);
+        }
+
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            syn::visit_mut::visit_expr_mut(self, expr);
+            let Expr::Macro(syn::ExprMacro { mac, .. }) = expr else {
+                return;
+            };
+            let tokens = mac.tokens.clone();
+            let expansion = if mac.path.is_ident("specialized_dispatch") {
+                syn::parse2::<SpecializedDispatchExpr>(tokens).map(ToTokens::into_token_stream)
+            } else if mac.path.is_ident("specialized_match") {
+                parse_specialized_match
+                    .parse2(tokens)
+                    .map(ToTokens::into_token_stream)
+            } else if mac.path.is_ident("dynamic_dispatch") {
+                syn::parse2::<DynamicDispatchExpr>(tokens).map(ToTokens::into_token_stream)
+            } else {
+                return;
+            };
+            *expr = syn::parse2(expansion.expect("failed to parse arguments of the macro"))
+                .expect("expansion should be an expression");
+        }
+
+        fn visit_block_mut(&mut self, block: &mut syn::Block) {
+            syn::visit_mut::visit_block_mut(self, block);
+            if let Some(syn::Stmt::Expr(Expr::Block(tail), None)) = block.stmts.last() {
+                if tail.attrs.is_empty() && tail.label.is_none() {
+                    let stmts = tail.block.stmts.clone();
+                    block.stmts.pop();
+                    block.stmts.extend(stmts);
+                }
+            }
+        }
+    }
+
+    /// Expands the macros in the given source file and pretty-prints the result.
+    fn expand_file(source: &str) -> String {
+        let mut file = syn::parse_file(source).expect("failed to parse the source file");
+        ExpandMacros.visit_file_mut(&mut file);
+        prettyplease::unparse(&file)
+    }
+
+    /// Replaces the code block following the given marker in `text`. Returns whether it changed.
+    fn replace_code_block(text: &mut String, marker: &str, code: &str) -> bool {
+        let start = text.find(marker).expect("marker should be present") + marker.len();
+        let start = start
+            + text[start..]
+                .find("```")
+                .expect("code block should follow marker");
+        let start = start
+            + text[start..]
+                .find('\n')
+                .expect("code block should be closed")
+            + 1;
+        let end = start
+            + text[start..]
+                .find("```")
+                .expect("code block should be closed");
+        if text[start..end] == *code {
+            return false;
+        }
+        text.replace_range(start..end, code);
+        true
+    }
+
+    /// Compares expansions of the files in `tests/expand` with the snapshots next to them, and
+    /// the expansion shown in README with the snapshot of the simple example. Snapshots are
+    /// overwritten instead if `UPDATE_SNAPSHOTS` environment variable is set.
+    #[test]
+    #[cfg_attr(
+        any(feature = "counters", feature = "tracing", feature = "derive"),
+        ignore = "expansions depend on enabled features"
+    )]
+    fn expansion_snapshots() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        let mut inputs: Vec<_> = fs::read_dir(root.join("tests/expand"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension().is_some_and(|ext| ext == "rs")
+                    && !path.to_string_lossy().ends_with(".expanded.rs")
+            })
+            .collect();
+        inputs.sort();
+        assert!(!inputs.is_empty());
+
+        let mut outdated = Vec::new();
+        for input in inputs {
+            let expanded = expand_file(&fs::read_to_string(&input).unwrap());
+            let snapshot = input.with_extension("expanded.rs");
+            if fs::read_to_string(&snapshot).ok().as_deref() == Some(&expanded) {
+                continue;
+            }
+            if update {
+                fs::write(&snapshot, &expanded).unwrap();
+            } else {
+                outdated.push(snapshot);
+            }
+        }
+
+        let readme_path = root.join("../README.md");
+        let mut readme = fs::read_to_string(&readme_path).unwrap();
+        let simple_example =
+            fs::read_to_string(root.join("tests/expand/simple_example.expanded.rs")).unwrap();
+        let marker = "<!-- expansion: macros/tests/expand/simple_example.expanded.rs -->";
+        if replace_code_block(&mut readme, marker, &simple_example) {
+            if update {
+                fs::write(&readme_path, readme).unwrap();
+            } else {
+                outdated.push(readme_path);
+            }
+        }
+
+        assert!(
+            outdated.is_empty(),
+            "outdated snapshots, set UPDATE_SNAPSHOTS=1 to overwrite them: {:?}",
+            outdated
+        );
+    }
+}
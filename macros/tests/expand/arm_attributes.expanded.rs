@@ -0,0 +1,24 @@
+fn example<E>(expr: E) -> String {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        #[cold]
+        default fn dispatch(_: Self) -> String {
+            format!("default value")
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        #[inline(always)]
+        fn dispatch(v: Self) -> String {
+            format!("u8: {}", v)
+        }
+    }
+    #[cfg(target_pointer_width = "64")]
+    impl SpecializedDispatchCall for u64 {
+        fn dispatch(v: Self) -> String {
+            format!("u64: {}", v)
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
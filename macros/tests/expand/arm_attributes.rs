@@ -0,0 +1,12 @@
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        #[cold]
+        default fn <T>(_: T) => format!("default value"),
+        #[inline(always)]
+        fn (v: u8) => format!("u8: {}", v),
+        #[cfg(target_pointer_width = "64")]
+        fn (v: u64) => format!("u64: {}", v),
+        expr,
+    )
+}
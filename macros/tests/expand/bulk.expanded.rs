@@ -0,0 +1,16 @@
+fn encode<E>(values: &[E], out: &mut Vec<u8>) {
+    trait SpecializedDispatchCall: ::core::marker::Sized {
+        fn dispatch(_: &[Self], out: &mut Vec<u8>) -> ();
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(v: &[Self], out: &mut Vec<u8>) -> () {
+            out.push(v.len() as u8)
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch(v: &[Self], out: &mut Vec<u8>) -> () {
+            out.extend_from_slice(v)
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(values, out)
+}
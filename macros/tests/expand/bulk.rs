@@ -0,0 +1,10 @@
+fn encode<E>(values: &[E], out: &mut Vec<u8>) {
+    specialized_dispatch!(
+        #[bulk]
+        &[E] -> (),
+        default fn <T>(v: &[T], out: &mut Vec<u8>) => out.push(v.len() as u8),
+        fn (v: &[u8], out: &mut Vec<u8>) => out.extend_from_slice(v),
+        values,
+        out,
+    )
+}
@@ -0,0 +1,28 @@
+fn example<E: Kind>(expr: E) -> String {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String;
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch(v: Self) -> String {
+            format!("u8: {}", v)
+        }
+    }
+    impl SpecializedDispatchCall for f32 {
+        fn dispatch(v: Self) -> String {
+            format!("f32: {}", v)
+        }
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(_: Self) -> String {
+            ::core::unreachable!()
+        }
+    }
+    struct ClosedSetArms;
+    impl ::specialized_dispatch::__private::ClosedSetArm<u8> for ClosedSetArms {}
+    impl ::specialized_dispatch::__private::ClosedSetArm<f32> for ClosedSetArms {}
+    fn check_closed_set<T: ?::core::marker::Sized + Kind>() {
+        <T as Kind>::__closed_set_arms::<ClosedSetArms>();
+    }
+    check_closed_set::<E>();
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
@@ -0,0 +1,9 @@
+fn example<E: Kind>(expr: E) -> String {
+    specialized_dispatch!(
+        #[closed(Kind)]
+        E -> String,
+        fn (v: u8) => format!("u8: {}", v),
+        fn (v: f32) => format!("f32: {}", v),
+        expr,
+    )
+}
@@ -0,0 +1,22 @@
+fn parse<E>(expr: E) -> Option<u8> {
+    let value = {
+        trait SpecializedDispatchCall {
+            fn dispatch(_: Self) -> ControlFlow<Option<u8>, u8>;
+        }
+        impl<T> SpecializedDispatchCall for T {
+            default fn dispatch(_: Self) -> ControlFlow<Option<u8>, u8> {
+                ControlFlow::Break(None)
+            }
+        }
+        impl SpecializedDispatchCall for u8 {
+            fn dispatch(v: Self) -> ControlFlow<Option<u8>, u8> {
+                ControlFlow::Continue(v)
+            }
+        }
+        match <E as SpecializedDispatchCall>::dispatch(expr) {
+            ::core::ops::ControlFlow::Continue(value) => value,
+            ::core::ops::ControlFlow::Break(value) => return value,
+        }
+    };
+    Some(value + 1)
+}
@@ -0,0 +1,10 @@
+fn parse<E>(expr: E) -> Option<u8> {
+    let value = specialized_dispatch!(
+        #[control_flow]
+        E -> ControlFlow<Option<u8>, u8>,
+        default fn <T>(_: T) => ControlFlow::Break(None),
+        fn (v: u8) => ControlFlow::Continue(v),
+        expr,
+    );
+    Some(value + 1)
+}
@@ -0,0 +1,35 @@
+fn example<E: Display>(expr: E) -> String {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String
+        where
+            Self: Display;
+    }
+    impl<T: Display> SpecializedDispatchCall for T {
+        default fn dispatch(v: Self) -> String {
+            format!("default value: {}", v)
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch(v: Self) -> String {
+            format!("u8: {}", v)
+        }
+    }
+    {
+        struct DefaultBoundsProbe<T: ?::core::marker::Sized>(
+            ::core::marker::PhantomData<T>,
+        );
+        trait SatisfiesDefaultBounds {
+            fn check(&self) {}
+        }
+        impl<T: Display> SatisfiesDefaultBounds for DefaultBoundsProbe<T> {}
+        trait UnreachableArm {
+            #[deprecated(
+                note = "this arm is never selected, since its type doesn't satisfy the bounds of the default arm"
+            )]
+            fn check(&self) {}
+        }
+        impl<T: ?::core::marker::Sized> UnreachableArm for &DefaultBoundsProbe<T> {}
+        (&DefaultBoundsProbe::<u8>(::core::marker::PhantomData)).check();
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
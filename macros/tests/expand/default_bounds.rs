@@ -0,0 +1,8 @@
+fn example<E: Display>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T: Display>(v: T) => format!("default value: {}", v),
+        fn (v: u8) => format!("u8: {}", v),
+        expr,
+    )
+}
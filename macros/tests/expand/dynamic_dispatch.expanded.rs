@@ -0,0 +1,21 @@
+fn example(value: Box<dyn Any>, prefix: &str) -> String {
+    fn arm0(v: &u8, prefix: &str) -> String {
+        format!("{}&u8: {}", prefix, v)
+    }
+    fn arm1(v: Box<u16>, prefix: &str) -> String {
+        format!("{}Box<u16>: {}", prefix, v)
+    }
+    fn arm2<T>(_: T, prefix: &str) -> String {
+        format!("{}default value", prefix)
+    }
+    let input: Box<dyn Any> = value;
+    let arg0 = prefix;
+    if let ::core::option::Option::Some(value) = input.downcast_ref::<u8>() {
+        arm0(value, arg0)
+    } else {
+        match input.downcast::<u16>() {
+            ::core::result::Result::Ok(value) => arm1(value, arg0),
+            ::core::result::Result::Err(input) => arm2(input, arg0),
+        }
+    }
+}
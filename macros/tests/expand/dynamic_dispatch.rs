@@ -0,0 +1,10 @@
+fn example(value: Box<dyn Any>, prefix: &str) -> String {
+    dynamic_dispatch!(
+        Box<dyn Any> -> String,
+        fn (v: &u8, prefix: &str) => format!("{}&u8: {}", prefix, v),
+        fn (v: Box<u16>, prefix: &str) => format!("{}Box<u16>: {}", prefix, v),
+        default fn <T>(_: T, prefix: &str) => format!("{}default value", prefix),
+        value,
+        prefix,
+    )
+}
@@ -0,0 +1,26 @@
+fn example<E>(expr: E, prefix: &str, mut count: u32) -> String {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self, prefix: &str, count: u32) -> String;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(_: Self, prefix: &str, count: u32) -> String {
+            format!("{}default value {}", prefix, count)
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch(mut v: Self, prefix: &str, count: u32) -> String {
+            {
+                v += 1;
+                format!("{}u8: {} {}", prefix, v, count)
+            }
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(
+        expr,
+        prefix,
+        {
+            count += 1;
+            count
+        },
+    )
+}
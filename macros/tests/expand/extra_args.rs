@@ -0,0 +1,13 @@
+fn example<E>(expr: E, prefix: &str, mut count: u32) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T, prefix: &str, count: u32) => format!("{}default value {}", prefix, count),
+        fn (mut v: u8, prefix: &str, count: u32) => {
+            v += 1;
+            format!("{}u8: {} {}", prefix, v, count)
+        },
+        expr,
+        prefix,
+        { count += 1; count },
+    )
+}
@@ -0,0 +1,31 @@
+fn example<E>(expr: E) -> String {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(_: Self) -> String {
+            format!("default value")
+        }
+    }
+    impl<T> SpecializedDispatchCall for Vec<T> {
+        fn dispatch(v: Self) -> String {
+            format!("Vec<T> of length {}", v.len())
+        }
+    }
+    impl<T> SpecializedDispatchCall for &[T] {
+        fn dispatch(v: Self) -> String {
+            format!("&[T] of length {}", v.len())
+        }
+    }
+    impl<const N: usize> SpecializedDispatchCall for [u8; N] {
+        fn dispatch(v: Self) -> String {
+            format!("[u8; {}]", N)
+        }
+    }
+    impl SpecializedDispatchCall for &str {
+        fn dispatch(v: Self) -> String {
+            format!("&str: {}", v)
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
@@ -0,0 +1,11 @@
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T) => format!("default value"),
+        fn <T>(v: Vec<T>) => format!("Vec<T> of length {}", v.len()),
+        fn <T>(v: &[T]) => format!("&[T] of length {}", v.len()),
+        fn <const N: usize>(v: [u8; N]) => format!("[u8; {}]", N),
+        fn (v: &str) => format!("&str: {}", v),
+        expr,
+    )
+}
@@ -0,0 +1,18 @@
+fn serialize<E: Debug, W: Write>(expr: E, writer: &mut W) -> io::Result<()> {
+    trait SpecializedDispatchCall {
+        fn dispatch<W: Write>(_: Self, writer: &mut W) -> io::Result<()>
+        where
+            Self: Debug;
+    }
+    impl<T: Debug> SpecializedDispatchCall for T {
+        default fn dispatch<W: Write>(v: Self, writer: &mut W) -> io::Result<()> {
+            write!(writer, "{:?}", v)
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch<W: Write>(v: Self, writer: &mut W) -> io::Result<()> {
+            writer.write_all(&[v])
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch::<W>(expr, writer)
+}
@@ -0,0 +1,9 @@
+fn serialize<E: Debug, W: Write>(expr: E, writer: &mut W) -> io::Result<()> {
+    specialized_dispatch!(
+        <W: Write> E -> io::Result<()>,
+        default fn <T: Debug>(v: T, writer: &mut W) => write!(writer, "{:?}", v),
+        fn (v: u8, writer: &mut W) => writer.write_all(&[v]),
+        expr,
+        writer,
+    )
+}
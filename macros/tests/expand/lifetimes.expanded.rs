@@ -0,0 +1,23 @@
+fn example<'a, E>(expr: E, out: &mut Vec<u8>, ctx: &'a Context) -> &'a str {
+    trait SpecializedDispatchCall {
+        fn dispatch<'a>(_: Self, _out: &mut Vec<u8>, ctx: &'a Context) -> &'a str;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch<'a>(
+            _: Self,
+            _out: &mut Vec<u8>,
+            ctx: &'a Context,
+        ) -> &'a str {
+            &ctx.name
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch<'a>(v: Self, out: &mut Vec<u8>, ctx: &'a Context) -> &'a str {
+            {
+                out.push(v);
+                &ctx.name[..1]
+            }
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr, out, ctx)
+}
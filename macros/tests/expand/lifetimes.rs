@@ -0,0 +1,13 @@
+fn example<'a, E>(expr: E, out: &mut Vec<u8>, ctx: &'a Context) -> &'a str {
+    specialized_dispatch!(
+        E -> &'a str,
+        default fn <T>(_: T, _out: &mut Vec<u8>, ctx: &'a Context) => &ctx.name,
+        fn (v: u8, out: &mut Vec<u8>, ctx: &'a Context) => {
+            out.push(v);
+            &ctx.name[..1]
+        },
+        expr,
+        out,
+        ctx,
+    )
+}
@@ -0,0 +1,21 @@
+fn serialize<E: Serialize<S>, S: Serializer>(
+    expr: E,
+    serializer: &S,
+) -> Result<Vec<u8>, S::Error> {
+    trait SpecializedDispatchCall<S: Serializer> {
+        fn dispatch(_: Self, serializer: &S) -> Result<Vec<u8>, S::Error>
+        where
+            Self: Serialize<S>;
+    }
+    impl<S: Serializer, T: Serialize<S>> SpecializedDispatchCall<S> for T {
+        default fn dispatch(v: Self, serializer: &S) -> Result<Vec<u8>, S::Error> {
+            v.serialize(serializer)
+        }
+    }
+    impl<S: Serializer> SpecializedDispatchCall<S> for u8 {
+        fn dispatch(v: Self, _serializer: &S) -> Result<Vec<u8>, S::Error> {
+            Ok(vec![v])
+        }
+    }
+    <E as SpecializedDispatchCall<S>>::dispatch(expr, serializer)
+}
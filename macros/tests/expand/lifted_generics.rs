@@ -0,0 +1,10 @@
+fn serialize<E: Serialize<S>, S: Serializer>(expr: E, serializer: &S) -> Result<Vec<u8>, S::Error> {
+    specialized_dispatch!(
+        #[lift(S: Serializer)]
+        E -> Result<Vec<u8>, S::Error>,
+        default fn <T: Serialize<S>>(v: T, serializer: &S) => v.serialize(serializer),
+        fn (v: u8, _serializer: &S) => Ok(vec![v]),
+        expr,
+        serializer,
+    )
+}
@@ -0,0 +1,29 @@
+fn example<E>(expr: E) -> String {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String;
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch(v: Self) -> String {
+            format!("u8: {}", v)
+        }
+    }
+    impl SpecializedDispatchCall for Vec<u8> {
+        fn dispatch(mut v: Self) -> String {
+            {
+                v.push(0);
+                format!("Vec<u8>: {:?}", v)
+            }
+        }
+    }
+    impl<T> SpecializedDispatchCall for Vec<T> {
+        fn dispatch(v: Self) -> String {
+            format!("Vec<T> of length {}", v.len())
+        }
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(_: Self) -> String {
+            format!("default value")
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
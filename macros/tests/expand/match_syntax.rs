@@ -0,0 +1,11 @@
+fn example<E>(expr: E) -> String {
+    specialized_match!(expr: E -> String {
+        v: u8 => format!("u8: {}", v),
+        mut v: Vec<u8> => {
+            v.push(0);
+            format!("Vec<u8>: {:?}", v)
+        }
+        <T> v: Vec<T> => format!("Vec<T> of length {}", v.len()),
+        _ => format!("default value"),
+    })
+}
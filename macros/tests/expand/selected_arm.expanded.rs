@@ -0,0 +1,34 @@
+fn example<E>(expr: E) -> (String, &'static str) {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String;
+        fn selected_arm() -> &'static str;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(_: Self) -> String {
+            format!("default value")
+        }
+        default fn selected_arm() -> &'static str {
+            "fallback"
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch(v: Self) -> String {
+            format!("u8: {}", v)
+        }
+        fn selected_arm() -> &'static str {
+            "u8"
+        }
+    }
+    impl SpecializedDispatchCall for &u8 {
+        fn dispatch(v: Self) -> String {
+            format!("&u8: {}", v)
+        }
+        fn selected_arm() -> &'static str {
+            "fast_path"
+        }
+    }
+    (
+        <E as SpecializedDispatchCall>::dispatch(expr),
+        <E as SpecializedDispatchCall>::selected_arm(),
+    )
+}
@@ -0,0 +1,10 @@
+fn example<E>(expr: E) -> (String, &'static str) {
+    specialized_dispatch!(
+        #[selected_arm]
+        E -> String,
+        default fn fallback <T>(_: T) => format!("default value"),
+        fn (v: u8) => format!("u8: {}", v),
+        fn fast_path(v: &u8) => format!("&u8: {}", v),
+        expr,
+    )
+}
@@ -0,0 +1,21 @@
+fn example<E>(expr: E) -> String {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(_: Self) -> String {
+            format!("default value")
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch(v: Self) -> String {
+            format!("u8: {}", v)
+        }
+    }
+    impl SpecializedDispatchCall for u16 {
+        fn dispatch(v: Self) -> String {
+            format!("u16: {}", v)
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
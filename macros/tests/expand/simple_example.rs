@@ -0,0 +1,9 @@
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T) => format!("default value"),
+        fn (v: u8) => format!("u8: {}", v),
+        fn (v: u16) => format!("u16: {}", v),
+        expr,
+    )
+}
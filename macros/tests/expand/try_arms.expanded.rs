@@ -0,0 +1,16 @@
+fn parse<E>(expr: E) -> Result<u8, Error> {
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> Result<u8, Error>;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        default fn dispatch(_: Self) -> Result<u8, Error> {
+            ::core::result::Result::Ok(0)
+        }
+    }
+    impl SpecializedDispatchCall for &str {
+        fn dispatch(v: Self) -> Result<u8, Error> {
+            ::core::result::Result::Ok(v.parse::<u8>()?)
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
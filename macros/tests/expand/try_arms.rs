@@ -0,0 +1,9 @@
+fn parse<E>(expr: E) -> Result<u8, Error> {
+    specialized_dispatch!(
+        #[try_arms]
+        E -> Result<u8, Error>,
+        default fn <T>(_: T) => 0,
+        fn (v: &str) => v.parse::<u8>()?,
+        expr,
+    )
+}
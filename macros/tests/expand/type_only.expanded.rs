@@ -0,0 +1,21 @@
+fn type_name<T>() -> &'static str {
+    trait SpecializedDispatchCall {
+        fn dispatch() -> &'static str;
+    }
+    impl<X> SpecializedDispatchCall for X {
+        default fn dispatch() -> &'static str {
+            "other"
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        fn dispatch() -> &'static str {
+            "u8"
+        }
+    }
+    impl<const N: usize> SpecializedDispatchCall for [u8; N] {
+        fn dispatch() -> &'static str {
+            "byte array"
+        }
+    }
+    <T as SpecializedDispatchCall>::dispatch()
+}
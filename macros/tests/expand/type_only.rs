@@ -0,0 +1,8 @@
+fn type_name<T>() -> &'static str {
+    specialized_dispatch!(
+        T -> &'static str,
+        default fn <X> => "other",
+        fn <u8> => "u8",
+        fn <const N: usize> <[u8; N]> => "byte array",
+    )
+}
@@ -0,0 +1,24 @@
+unsafe fn example<E>(expr: E) -> String {
+    trait SpecializedDispatchCall {
+        unsafe fn dispatch(_: Self) -> String;
+    }
+    impl<T> SpecializedDispatchCall for T {
+        #[deny(unsafe_op_in_unsafe_fn)]
+        default unsafe fn dispatch(_: Self) -> String {
+            format!("default value")
+        }
+    }
+    impl SpecializedDispatchCall for *const u8 {
+        #[deny(unsafe_op_in_unsafe_fn)]
+        unsafe fn dispatch(v: Self) -> String {
+            unsafe { format!("*const u8: {}", v.read()) }
+        }
+    }
+    impl SpecializedDispatchCall for u8 {
+        #[deny(unsafe_op_in_unsafe_fn)]
+        unsafe fn dispatch(v: Self) -> String {
+            format!("u8: {}", v)
+        }
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
@@ -0,0 +1,9 @@
+unsafe fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        unsafe E -> String,
+        default fn <T>(_: T) => format!("default value"),
+        unsafe fn (v: *const u8) => format!("*const u8: {}", v.read()),
+        fn (v: u8) => format!("u8: {}", v),
+        expr,
+    )
+}
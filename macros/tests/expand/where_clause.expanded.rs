@@ -0,0 +1,48 @@
+fn example<E>(expr: E) -> String
+where
+    E: IntoIterator,
+    E::Item: Debug,
+{
+    trait SpecializedDispatchCall {
+        fn dispatch(_: Self) -> String
+        where
+            Self: IntoIterator,
+            Self::Item: Debug;
+    }
+    impl<T> SpecializedDispatchCall for T
+    where
+        T: IntoIterator,
+        T::Item: Debug,
+    {
+        default fn dispatch(v: Self) -> String {
+            { format!("first: {:?}", v.into_iter().next()) }
+        }
+    }
+    impl SpecializedDispatchCall for Vec<u8> {
+        fn dispatch(v: Self) -> String {
+            format!("Vec<u8> of length {}", v.len())
+        }
+    }
+    {
+        struct DefaultBoundsProbe<T: ?::core::marker::Sized>(
+            ::core::marker::PhantomData<T>,
+        );
+        trait SatisfiesDefaultBounds {
+            fn check(&self) {}
+        }
+        impl<T> SatisfiesDefaultBounds for DefaultBoundsProbe<T>
+        where
+            T: IntoIterator,
+            T::Item: Debug,
+        {}
+        trait UnreachableArm {
+            #[deprecated(
+                note = "this arm is never selected, since its type doesn't satisfy the bounds of the default arm"
+            )]
+            fn check(&self) {}
+        }
+        impl<T: ?::core::marker::Sized> UnreachableArm for &DefaultBoundsProbe<T> {}
+        (&DefaultBoundsProbe::<Vec<u8>>(::core::marker::PhantomData)).check();
+    }
+    <E as SpecializedDispatchCall>::dispatch(expr)
+}
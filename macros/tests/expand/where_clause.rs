@@ -0,0 +1,14 @@
+fn example<E>(expr: E) -> String
+where
+    E: IntoIterator,
+    E::Item: Debug,
+{
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(v: T) where T: IntoIterator, T::Item: Debug => {
+            format!("first: {:?}", v.into_iter().next())
+        },
+        fn (v: Vec<u8>) => format!("Vec<u8> of length {}", v.len()),
+        expr,
+    )
+}
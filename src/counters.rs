@@ -0,0 +1,156 @@
+//! Counters of how many times each arm of each dispatch is selected.
+//!
+//! When `counters` feature is enabled, every dispatch counts how many times each of its arms is
+//! selected. The counts can be inspected with [`snapshot`], e.g. to confirm how often specialized
+//! arms are hit compared to the default arm:
+//!
+//! ```
+//! #![feature(min_specialization)]
+//!
+//! use specialized_dispatch::{counters, specialized_dispatch};
+//!
+//! fn example<E>(expr: E) -> String {
+//!     specialized_dispatch!(
+//!         E -> String,
+//!         default fn <T>(_: T) => format!("default value"),
+//!         fn (v: u8) => format!("u8: {}", v),
+//!         expr,
+//!     )
+//! }
+//!
+//! example(1u8);
+//! example(2u8);
+//! example(1.5);
+//!
+//! for count in counters::snapshot() {
+//!     // Prints e.g. `src/main.rs:6:5 u8: 2`
+//!     println!("{}", count);
+//! }
+//!
+//! let counts: Vec<_> = counters::snapshot()
+//!     .into_iter()
+//!     .map(|count| (count.arm, count.count))
+//!     .collect();
+//! assert_eq!(counts, [("T", 1), ("u8", 2)]);
+//! ```
+//!
+//! A dispatch is registered, and hence reported, once any of its arms is selected for the first
+//! time.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, Once,
+    },
+};
+
+/// Location of a dispatch in the source code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Number of times an arm of a dispatch is selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArmCount {
+    /// Location of the dispatch.
+    pub location: Location,
+    /// Label of the arm, which is the name given to the arm or its type.
+    pub arm: &'static str,
+    /// Number of times the arm is selected.
+    pub count: u64,
+}
+
+impl fmt::Display for ArmCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.location, self.arm, self.count)
+    }
+}
+
+/// Counters of a registered dispatch, with the number of arms erased.
+struct Registered {
+    location: &'static Location,
+    labels: &'static [&'static str],
+    counts: &'static [AtomicU64],
+}
+
+static REGISTRY: Mutex<Vec<Registered>> = Mutex::new(Vec::new());
+
+/// Counters of a single dispatch with `N` arms. This is used by the code generated by the macros
+/// and isn't meant to be used directly.
+#[doc(hidden)]
+pub struct DispatchCounters<const N: usize> {
+    location: Location,
+    labels: [&'static str; N],
+    counts: [AtomicU64; N],
+    registered: Once,
+}
+
+impl<const N: usize> DispatchCounters<N> {
+    pub const fn new(
+        file: &'static str,
+        line: u32,
+        column: u32,
+        labels: [&'static str; N],
+    ) -> Self {
+        Self {
+            location: Location { file, line, column },
+            labels,
+            counts: [const { AtomicU64::new(0) }; N],
+            registered: Once::new(),
+        }
+    }
+
+    /// Counts a selection of the arm at the given index.
+    #[inline]
+    pub fn hit(&'static self, arm: usize) {
+        self.registered.call_once(|| {
+            REGISTRY
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(Registered {
+                    location: &self.location,
+                    labels: &self.labels,
+                    counts: &self.counts,
+                });
+        });
+        self.counts[arm].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Returns the counts of all arms of the dispatches selected so far, in the order of dispatches
+/// registered and the order of arms within each dispatch.
+pub fn snapshot() -> Vec<ArmCount> {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    registry
+        .iter()
+        .flat_map(|dispatch| {
+            dispatch
+                .labels
+                .iter()
+                .zip(dispatch.counts)
+                .map(|(arm, count)| ArmCount {
+                    location: *dispatch.location,
+                    arm,
+                    count: count.load(Ordering::Relaxed),
+                })
+        })
+        .collect()
+}
+
+/// Resets the counts of all arms to zero.
+pub fn reset() {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    for count in registry.iter().flat_map(|dispatch| dispatch.counts) {
+        count.store(0, Ordering::Relaxed);
+    }
+}
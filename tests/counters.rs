@@ -0,0 +1,61 @@
+#![cfg(feature = "counters")]
+#![feature(min_specialization)]
+
+use specialized_dispatch::{counters, dynamic_dispatch, specialized_dispatch};
+
+// Counters are global, so everything is checked in a single test to avoid interference.
+#[test]
+fn test_counters() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn (v: u8) => format!("u8: {}", v),
+            fn fast_path(v: u16) => format!("u16: {}", v),
+            expr,
+        )
+    }
+
+    fn example_dyn(value: &dyn std::any::Any) -> &'static str {
+        dynamic_dispatch!(
+            &dyn std::any::Any -> &'static str,
+            fn (_: &u8) => "u8",
+            default fn <T>(_: T) => "other",
+            value,
+        )
+    }
+
+    let counts = || -> Vec<_> {
+        counters::snapshot()
+            .into_iter()
+            .map(|count| {
+                assert_eq!(count.location.file, file!());
+                (count.arm, count.count)
+            })
+            .collect()
+    };
+
+    assert_eq!(counts(), []);
+
+    example(1u8);
+    example(2u8);
+    example(1.5);
+    assert_eq!(counts(), [("T", 1), ("u8", 2), ("fast_path", 0)]);
+
+    example_dyn(&1u8);
+    example(3u16);
+    assert_eq!(
+        counts(),
+        [("T", 1), ("u8", 2), ("fast_path", 1), ("&u8", 1), ("T", 0)]
+    );
+
+    let locations: Vec<_> = counters::snapshot().iter().map(|c| c.location).collect();
+    assert_eq!(locations[0], locations[2]);
+    assert_ne!(locations[0], locations[3]);
+
+    counters::reset();
+    assert_eq!(
+        counts(),
+        [("T", 0), ("u8", 0), ("fast_path", 0), ("&u8", 0), ("T", 0)]
+    );
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "derive")]
+#![feature(min_specialization)]
+
+use specialized_dispatch::{specialized_dispatch, specialized_match, SpecializedDispatch};
+
+#[derive(SpecializedDispatch)]
+struct Id<T>(T);
+
+#[derive(SpecializedDispatch)]
+struct Sorted<T> {
+    items: Vec<T>,
+}
+
+#[derive(SpecializedDispatch)]
+struct Name<'a>(&'a str);
+
+#[test]
+fn test_newtypes() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn (v: u8) => format!("u8: {}", v),
+            fn <T>(v: Vec<T>) => format!("vec of length {}", v.len()),
+            fn (v: &str) => format!("str: {}", v),
+            // Arms for wrappers are preferred over their inner types.
+            fn (v: Id<u16>) => format!("Id<u16>: {}", v.0),
+            expr,
+        )
+    }
+
+    assert_eq!(example(Id(5u8)), "u8: 5");
+    assert_eq!(example(Id(Id(5u8))), "u8: 5");
+    assert_eq!(example(Id(vec![1u32, 2])), "vec of length 2");
+    assert_eq!(example(Sorted { items: vec![1u8] }), "vec of length 1");
+    assert_eq!(example(Name("bob")), "str: bob");
+    assert_eq!(example(Id(5u16)), "Id<u16>: 5");
+    assert_eq!(example(Id(Id(5u16))), "Id<u16>: 5");
+    assert_eq!(example(Id(5u32)), "default value");
+}
+
+#[test]
+fn test_newtypes_variants() {
+    fn matched<E>(expr: E) -> (u8, &'static str) {
+        specialized_match!(
+            #[selected_arm]
+            expr: E -> u8 {
+                v: u8 => v + 1,
+                _ => 0,
+            }
+        )
+    }
+
+    fn extra_args<E>(expr: E, offset: u8) -> u8 {
+        specialized_dispatch!(
+            E -> u8,
+            default fn <T>(_: T, _: u8) => 0,
+            fn (v: u8, offset: u8) => v + offset,
+            expr,
+            offset,
+        )
+    }
+
+    fn type_only<E>() -> &'static str {
+        specialized_dispatch!(
+            E -> &'static str,
+            default fn <T> => "default",
+            fn <u8> => "u8",
+        )
+    }
+
+    unsafe fn unsafe_dispatch<E>(expr: E) -> u8 {
+        unsafe {
+            specialized_dispatch!(
+                unsafe E -> u8,
+                default fn <T>(_: T) => 0,
+                unsafe fn (v: *const u8) => *v,
+                expr,
+            )
+        }
+    }
+
+    assert_eq!(matched(Id(5u8)), (6, "u8"));
+    assert_eq!(matched(Id(5u16)), (0, "T"));
+    assert_eq!(extra_args(Id(5u8), 1), 6);
+    assert_eq!(type_only::<Id<u8>>(), "u8");
+    assert_eq!(type_only::<Id<u16>>(), "default");
+    let value = 5u8;
+    assert_eq!(unsafe { unsafe_dispatch(Id(&value as *const u8)) }, 5);
+}
+
+#[test]
+fn test_newtypes_with_default_bounds() {
+    use std::fmt::Debug;
+
+    // Wrappers can't be forwarded to the inner type if the default arm has bounds.
+    fn example<E: Debug>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T: Debug>(v: T) => format!("default value: {:?}", v),
+            fn (v: u8) => format!("u8: {}", v),
+            expr,
+        )
+    }
+
+    #[derive(Debug, SpecializedDispatch)]
+    struct Debugged(u8);
+
+    assert_eq!(example(Debugged(5)), "default value: Debugged(5)");
+}
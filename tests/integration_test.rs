@@ -2,6 +2,11 @@
 
 use specialized_dispatch::specialized_dispatch;
 
+specialized_dispatch::check_setup!();
+
+// Calls `check_setup!()` from a module file.
+mod setup;
+
 #[test]
 fn test_example() {
     fn example<E>(expr: E) -> String {
@@ -97,6 +102,61 @@ fn test_bound_traits_with_generic() {
     assert_eq!(example(10u16), "u16: 10");
 }
 
+#[test]
+fn test_bound_traits_naming_type_param() {
+    fn example<E: PartialEq<E> + Clone>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T: PartialEq<T> + Clone>(v: T) => format!("{}", v.clone() == v),
+            fn (v: u8) => format!("u8: {}", v),
+            expr,
+        )
+    }
+
+    assert_eq!(example(1.5), "true");
+    assert_eq!(example(5u8), "u8: 5");
+}
+
+#[test]
+fn test_arms_of_same_named_types() {
+    fn example<E>(expr: E) -> &'static str {
+        specialized_dispatch!(
+            E -> &'static str,
+            default fn <T>(_: T) => "default",
+            // Different types, although both are named `IntoIter`.
+            fn (_: std::vec::IntoIter<u8>) => "vec",
+            fn (_: std::option::IntoIter<u8>) => "option",
+            expr,
+        )
+    }
+
+    assert_eq!(example(vec![1u8].into_iter()), "vec");
+    assert_eq!(example(Some(1u8).into_iter()), "option");
+    assert_eq!(example(1u8), "default");
+}
+
+#[test]
+fn test_arm_unsatisfied_default_bounds() {
+    use std::fmt::Display;
+
+    struct Meters(f64);
+
+    #[allow(deprecated)]
+    fn example<E: Display>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T: Display>(v: T) => format!("default value: {}", v),
+            fn (v: u8) => format!("u8: {}", v),
+            // Never selected, since `Meters` doesn't implement `Display`.
+            fn (v: Meters) => format!("{} meters", v.0),
+            expr,
+        )
+    }
+
+    assert_eq!(example(1.5), "default value: 1.5");
+    assert_eq!(example(5u8), "u8: 5");
+}
+
 #[test]
 fn test_extra_args() {
     use std::fmt::Display;
@@ -132,3 +192,1511 @@ fn test_extra_args_with_str_arg() {
     assert_eq!(example(5u8, "ceviz"), "u8: 5, arg: ceviz");
     assert_eq!(example(10u16, "agaciyim"), "u16: 10, arg: agaciyim");
 }
+
+#[test]
+fn test_extra_args_of_dispatched_type() {
+    fn example<E>(a: E, b: E) -> &'static str {
+        specialized_dispatch!(
+            E -> &'static str,
+            fn (_: u8, _: u8) => "u8",
+            default fn <T>(_: T, _: T) => "default",
+            a,
+            b,
+        )
+    }
+
+    fn renamed<E>(a: E, b: Option<E>) -> &'static str {
+        specialized_dispatch!(
+            E -> &'static str,
+            default fn <U>(_: U, _: Option<U>) => "default",
+            fn (_: u8, _: Option<u8>) => "u8",
+            a,
+            b,
+        )
+    }
+
+    assert_eq!(example(1u8, 2u8), "u8");
+    assert_eq!(example(1u16, 2u16), "default");
+    assert_eq!(renamed(1u8, None), "u8");
+    assert_eq!(renamed(1u16, Some(2u16)), "default");
+}
+
+#[test]
+fn test_match_syntax() {
+    use specialized_dispatch::specialized_match;
+
+    fn example<E>(expr: E) -> String {
+        specialized_match!(expr: E -> String {
+            v: u8 => format!("u8: {}", v),
+            v: u16 => format!("u16: {}", v),
+            _ => format!("default value"),
+        })
+    }
+
+    assert_eq!(example(1.0), "default value");
+    assert_eq!(example(5u8), "u8: 5");
+    assert_eq!(example(10u16), "u16: 10");
+}
+
+#[test]
+fn test_match_syntax_with_bound_default() {
+    use specialized_dispatch::specialized_match;
+    use std::fmt::Display;
+
+    fn example<E: Display>(expr: E) -> String {
+        specialized_match!(expr: E -> String {
+            default <T: Display> v: T => format!("default value: {}", v),
+            mut v: u8 => {
+                v += 1;
+                format!("u8 + 1: {}", v)
+            }
+            v: u16 => format!("u16: {}", v),
+        })
+    }
+
+    assert_eq!(example(1.5), "default value: 1.5");
+    assert_eq!(example(5u8), "u8 + 1: 6");
+    assert_eq!(example(10u16), "u16: 10");
+}
+
+#[test]
+fn test_type_only() {
+    fn example<E>() -> &'static str {
+        specialized_dispatch!(
+            E -> &'static str,
+            fn <u8> => "u8",
+            fn <u16> => "u16",
+            default fn <T> => "other",
+        )
+    }
+
+    assert_eq!(example::<u8>(), "u8");
+    assert_eq!(example::<u16>(), "u16");
+    assert_eq!(example::<f32>(), "other");
+}
+
+#[test]
+fn test_type_only_with_bound_default() {
+    use std::fmt::Debug;
+
+    fn example<E: Default + Debug>() -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T: Default + Debug> => format!("default value: {:?}", T::default()),
+            fn <u8> => format!("u8: {}", u8::MAX),
+        )
+    }
+
+    assert_eq!(example::<u8>(), "u8: 255");
+    assert_eq!(example::<bool>(), "default value: false");
+}
+
+#[test]
+fn test_const_generic_arms() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            default fn <const N: usize>(v: [u8; N]) => format!("[u8; {}]: {:?}", N, v),
+            fn (v: [u8; 4]) => format!("[u8; 4]: {:?}", v),
+            fn <const N: usize>(v: [u16; N]) => format!("[u16; {}]: {:?}", N, v),
+            expr,
+        )
+    }
+
+    assert_eq!(example([1u8, 2]), "[u8; 2]: [1, 2]");
+    assert_eq!(example([1u8, 2, 3, 4]), "[u8; 4]: [1, 2, 3, 4]");
+    assert_eq!(example([1u16, 2, 3]), "[u16; 3]: [1, 2, 3]");
+    assert_eq!(example([1u32, 2]), "default value");
+}
+
+#[test]
+fn test_wrapper_type_arms() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn (v: Vec<u8>) => format!("Vec<u8>: {:?}", v),
+            fn <T>(v: Vec<Vec<T>>) => format!("Vec<Vec<T>> of length {}", v.len()),
+            fn (v: Box<str>) => format!("Box<str>: {}", v),
+            fn (v: Option<u8>) => format!("Option<u8>: {:?}", v),
+            expr,
+        )
+    }
+
+    assert_eq!(example(vec![1u8, 2]), "Vec<u8>: [1, 2]");
+    assert_eq!(example(vec![vec![1u32], vec![]]), "Vec<Vec<T>> of length 2");
+    assert_eq!(example(Box::<str>::from("kutu")), "Box<str>: kutu");
+    assert_eq!(example(Some(3u8)), "Option<u8>: Some(3)");
+    assert_eq!(example(vec![1u16]), "default value");
+}
+
+#[test]
+fn test_reference_arms() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn <T>(v: &[T]) => format!("&[T] of length {}", v.len()),
+            fn (v: &str) => format!("&str: {}", v),
+            fn (v: &mut Vec<u8>) => {
+                v.push(0);
+                format!("&mut Vec<u8>: {:?}", v)
+            },
+            expr,
+        )
+    }
+
+    assert_eq!(example(&[1u8, 2, 3][..]), "&[T] of length 3");
+    assert_eq!(example("dilim"), "&str: dilim");
+    assert_eq!(example(&mut vec![1u8]), "&mut Vec<u8>: [1, 0]");
+    assert_eq!(example(&1u8), "default value");
+}
+
+#[test]
+fn test_dynamic_dispatch_ref() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+
+    fn example(value: &dyn Any, arg: &str) -> String {
+        dynamic_dispatch!(
+            &dyn Any -> String,
+            fn (v: &u8, arg: &str) => format!("u8: {}, arg: {}", v, arg),
+            fn (v: &String, arg: &str) => format!("String: {}, arg: {}", v, arg),
+            default fn <T>(_: T, arg: &str) => format!("default value, arg: {}", arg),
+            value, arg,
+        )
+    }
+
+    assert_eq!(example(&5u8, "bir"), "u8: 5, arg: bir");
+    assert_eq!(example(&String::from("iki"), "uc"), "String: iki, arg: uc");
+    assert_eq!(example(&5u16, "dort"), "default value, arg: dort");
+}
+
+#[test]
+fn test_dynamic_dispatch_mut() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+
+    fn example(value: &mut dyn Any) -> usize {
+        dynamic_dispatch!(
+            &mut dyn Any -> usize,
+            fn (v: &mut Vec<u8>) => {
+                v.push(0);
+                v.len()
+            },
+            default fn <T>(_: T) => 0,
+            value,
+        )
+    }
+
+    let mut v = vec![1u8];
+    assert_eq!(example(&mut v), 2);
+    assert_eq!(v, [1, 0]);
+    assert_eq!(example(&mut 1u8), 0);
+}
+
+#[test]
+fn test_dynamic_dispatch_box() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+
+    fn example(value: Box<dyn Any + Send>) -> String {
+        dynamic_dispatch!(
+            Box<dyn Any + Send> -> String,
+            fn (v: Box<u8>) => format!("Box<u8>: {}", v),
+            fn (v: &u16) => format!("&u16: {}", v),
+            default fn <T: std::fmt::Debug>(v: T) => format!("default value: {:?}", v),
+            value,
+        )
+    }
+
+    assert_eq!(example(Box::new(5u8)), "Box<u8>: 5");
+    assert_eq!(example(Box::new(10u16)), "&u16: 10");
+    assert_eq!(example(Box::new(1.5)), "default value: Any { .. }");
+}
+
+#[test]
+// The second arm is never selected, which is warned about.
+#[allow(deprecated)]
+fn test_dynamic_dispatch_declaration_order() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+
+    let value: &dyn Any = &5u8;
+    let s = dynamic_dispatch!(
+        &dyn Any -> &'static str,
+        fn (_: &u8) => "first",
+        fn (_: &u8) => "second",
+        default fn <T>(_: T) => "other",
+        value,
+    );
+    assert_eq!(s, "first");
+}
+
+#[test]
+fn test_selected_arm() {
+    fn example<E>(expr: E) -> (String, &'static str) {
+        specialized_dispatch!(
+            #[selected_arm]
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn (v: u8) => format!("u8: {}", v),
+            fn fast_path(v: &u8) => format!("&u8: {}", v),
+            fn <T>(v: &[T]) => format!("&[T] of length {}", v.len()),
+            expr,
+        )
+    }
+
+    assert_eq!(example(5u8), ("u8: 5".to_string(), "u8"));
+    assert_eq!(example(&5u8), ("&u8: 5".to_string(), "fast_path"));
+    assert_eq!(
+        example(&[1u8][..]),
+        ("&[T] of length 1".to_string(), "&[T]")
+    );
+    assert_eq!(example(5u16), ("default value".to_string(), "T"));
+}
+
+#[test]
+fn test_selected_arm_type_only() {
+    fn example<E>() -> &'static str {
+        let ((), arm) = specialized_dispatch!(
+            #[selected_arm]
+            E -> (),
+            default fn fallback <T> => (),
+            fn <Vec<u8>> => (),
+        );
+        arm
+    }
+
+    assert_eq!(example::<Vec<u8>>(), "Vec<u8>");
+    assert_eq!(example::<Vec<u16>>(), "fallback");
+}
+
+#[test]
+fn test_selected_arm_match_syntax() {
+    use specialized_dispatch::specialized_match;
+
+    fn example<E>(expr: E) -> (String, &'static str) {
+        specialized_match!(#[selected_arm] expr: E -> String {
+            v: u8 => format!("u8: {}", v),
+            _ => format!("default value"),
+        })
+    }
+
+    assert_eq!(example(5u8), ("u8: 5".to_string(), "u8"));
+    assert_eq!(example(5u16), ("default value".to_string(), "T"));
+}
+
+#[test]
+fn test_selected_arm_dynamic_dispatch() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+
+    fn example(value: &dyn Any) -> &'static str {
+        let ((), arm) = dynamic_dispatch!(
+            #[selected_arm]
+            &dyn Any -> (),
+            fn (_: &u8) => (),
+            fn pair(_: &(u8, u8)) => (),
+            default fn <T>(_: T) => (),
+            value,
+        );
+        arm
+    }
+
+    assert_eq!(example(&5u8), "&u8");
+    assert_eq!(example(&(1u8, 2u8)), "pair");
+    assert_eq!(example(&5u16), "T");
+}
+
+#[test]
+fn test_closed() {
+    use specialized_dispatch::closed_set;
+
+    closed_set!(trait Kind for u8, u16, Vec<u8>, &str);
+
+    fn example<E: Kind>(expr: E, suffix: &str) -> String {
+        specialized_dispatch!(
+            #[closed(Kind)]
+            E -> String,
+            fn (v: u8, suffix: &str) => format!("u8: {}{}", v, suffix),
+            fn (v: u16, suffix: &str) => format!("u16: {}{}", v, suffix),
+            fn <T>(v: Vec<T>, suffix: &str) => format!("Vec<T> of length {}{}", v.len(), suffix),
+            fn (v: &str, suffix: &str) => format!("&str: {}{}", v, suffix),
+            expr,
+            suffix,
+        )
+    }
+
+    assert_eq!(example(5u8, "!"), "u8: 5!");
+    assert_eq!(example(10u16, "!"), "u16: 10!");
+    assert_eq!(example(vec![1u8, 2u8], "!"), "Vec<T> of length 2!");
+    assert_eq!(example("hello", "!"), "&str: hello!");
+}
+
+mod kinds {
+    use specialized_dispatch::closed_set;
+
+    closed_set!(
+        /// Types accepted by `describe`.
+        pub trait Kind for u8, u16, Vec<u8>, &str
+    );
+}
+
+#[test]
+fn test_closed_set() {
+    // The trait of the set can be named by a path.
+    fn example<E: kinds::Kind>(expr: E) -> String {
+        specialized_dispatch!(
+            #[closed(kinds::Kind)]
+            E -> String,
+            fn (v: u8) => format!("u8: {}", v),
+            fn (v: u16) => format!("u16: {}", v),
+            fn (v: Vec<u8>) => format!("Vec<u8> of length {}", v.len()),
+            fn (v: &str) => format!("&str: {}", v),
+            expr,
+        )
+    }
+
+    assert_eq!(example(5u8), "u8: 5");
+    assert_eq!(example(10u16), "u16: 10");
+    assert_eq!(example(vec![1u8, 2u8]), "Vec<u8> of length 2");
+    assert_eq!(example("hello"), "&str: hello");
+}
+
+#[test]
+fn test_closed_with_default() {
+    use specialized_dispatch::closed_set;
+
+    closed_set!(trait Kind for u8, u16, f32);
+
+    fn example<E: Kind>(expr: E) -> (String, &'static str) {
+        specialized_dispatch!(
+            #[closed(Kind)]
+            #[selected_arm]
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn (v: u8) => format!("u8: {}", v),
+            expr,
+        )
+    }
+
+    assert_eq!(example(5u8), ("u8: 5".to_string(), "u8"));
+    assert_eq!(example(10u16), ("default value".to_string(), "T"));
+    assert_eq!(example(1.5f32), ("default value".to_string(), "T"));
+}
+
+#[test]
+fn test_closed_type_only() {
+    use specialized_dispatch::closed_set;
+
+    closed_set!(trait Kind for u8, u16, [u8; 4]);
+
+    fn size_name<E: Kind>() -> &'static str {
+        specialized_dispatch!(
+            #[closed(Kind)]
+            E -> &'static str,
+            fn <u8> => "byte",
+            fn <u16> => "word",
+            fn <const N: usize> <[u8; N]> => "bytes",
+        )
+    }
+
+    assert_eq!(size_name::<u8>(), "byte");
+    assert_eq!(size_name::<u16>(), "word");
+    assert_eq!(size_name::<[u8; 4]>(), "bytes");
+}
+
+#[test]
+fn test_closed_match_syntax() {
+    use specialized_dispatch::{closed_set, specialized_match};
+
+    closed_set!(trait Kind for u8, f32);
+
+    fn example<E: Kind>(expr: E) -> String {
+        specialized_match!(#[closed(Kind)] expr: E -> String {
+            v: u8 => format!("u8: {}", v),
+            v: f32 => format!("f32: {:.1}", v),
+        })
+    }
+
+    assert_eq!(example(5u8), "u8: 5");
+    assert_eq!(example(1.5f32), "f32: 1.5");
+}
+
+#[test]
+fn test_where_clause() {
+    use std::fmt::Debug;
+
+    fn example<E>(expr: E) -> String
+    where
+        E: IntoIterator,
+        E::Item: Debug,
+    {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(v: T) where T: IntoIterator, T::Item: Debug => {
+                format!("first: {:?}", v.into_iter().next())
+            },
+            fn (v: Vec<u8>) => format!("Vec<u8> of length {}", v.len()),
+            expr,
+        )
+    }
+
+    assert_eq!(example([1u16, 2]), "first: Some(1)");
+    assert_eq!(example(vec![1u8, 2]), "Vec<u8> of length 2");
+}
+
+#[test]
+fn test_where_clause_higher_ranked() {
+    fn sum<E>(expr: E) -> u32
+    where
+        for<'a> &'a E: IntoIterator<Item = &'a u8>,
+    {
+        specialized_dispatch!(
+            E -> u32,
+            default fn <T>(v: T) where for<'a> &'a T: IntoIterator<Item = &'a u8> => {
+                (&v).into_iter().map(|x| *x as u32).sum()
+            },
+            fn <const N: usize>(_: [u8; N]) => 0,
+            expr,
+        )
+    }
+
+    assert_eq!(sum(vec![1u8, 2, 3]), 6);
+    assert_eq!(sum([1u8, 2, 3]), 0);
+}
+
+#[test]
+fn test_where_clause_type_only_and_match_syntax() {
+    use specialized_dispatch::specialized_match;
+    use std::fmt::Debug;
+
+    fn name<E>() -> &'static str
+    where
+        E: Default + Debug,
+    {
+        specialized_dispatch!(
+            E -> &'static str,
+            default fn <T> where T: Default + Debug => "other",
+            fn <u8> => "u8",
+        )
+    }
+
+    fn example<E: Debug>(expr: E) -> String {
+        specialized_match!(expr: E -> String {
+            v: u8 => format!("u8: {}", v),
+            default <T> v: T where T: Debug => format!("default value: {:?}", v),
+        })
+    }
+
+    assert_eq!(name::<u8>(), "u8");
+    assert_eq!(name::<String>(), "other");
+    assert_eq!(example(5u8), "u8: 5");
+    assert_eq!(example("a"), "default value: \"a\"");
+}
+
+#[test]
+fn test_where_clause_dynamic_dispatch() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+    use std::fmt::Debug;
+
+    fn example(value: &dyn Any) -> String {
+        dynamic_dispatch!(
+            &dyn Any -> String,
+            fn (v: &u8) => format!("u8: {}", v),
+            default fn <T>(v: T) where T: Debug => format!("default value: {:?}", v),
+            value,
+        )
+    }
+
+    assert_eq!(example(&5u8), "u8: 5");
+    assert_eq!(example(&5u16), "default value: Any { .. }");
+}
+
+#[test]
+fn test_arm_attributes() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            #[cold]
+            default fn <T>(_: T) => format!("default value"),
+            #[inline(always)]
+            fn (v: u8) => format!("u8: {}", v),
+            /// Arms can be documented, too.
+            #[allow(clippy::useless_format)]
+            fn (_: u16) => format!("u16"),
+            expr,
+        )
+    }
+
+    assert_eq!(example(5u8), "u8: 5");
+    assert_eq!(example(5u16), "u16");
+    assert_eq!(example(5u32), "default value");
+}
+
+#[test]
+fn test_arm_cfg() {
+    fn example<E>(expr: E) -> &'static str {
+        specialized_dispatch!(
+            E -> &'static str,
+            default fn <T>(_: T) => "default",
+            #[cfg(all())]
+            fn (_: u8) => "enabled",
+            #[cfg(any())]
+            fn (_: u8) => "disabled",
+            #[cfg(any())]
+            fn (_: u16) => "disabled",
+            expr,
+        )
+    }
+
+    assert_eq!(example(5u8), "enabled");
+    assert_eq!(example(5u16), "default");
+}
+
+#[test]
+fn test_arm_cfg_match_syntax() {
+    use specialized_dispatch::specialized_match;
+
+    fn example<E>(expr: E) -> &'static str {
+        specialized_match!(expr: E -> &'static str {
+            #[cfg(any())]
+            _: u8 => "disabled",
+            #[inline]
+            _: u16 => "u16",
+            _ => "default",
+        })
+    }
+
+    assert_eq!(example(5u8), "default");
+    assert_eq!(example(5u16), "u16");
+}
+
+#[test]
+fn test_arm_attributes_dynamic_dispatch() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+
+    fn example(value: &dyn Any) -> String {
+        dynamic_dispatch!(
+            &dyn Any -> String,
+            #[inline(always)]
+            fn (v: &u8) => format!("u8: {}", v),
+            #[cold]
+            default fn <T>(_: T) => format!("default value"),
+            value,
+        )
+    }
+
+    assert_eq!(example(&5u8), "u8: 5");
+    assert_eq!(example(&5u16), "default value");
+}
+
+#[test]
+fn test_unsafe_dispatch() {
+    /// # Safety
+    ///
+    /// Raw pointers must be valid for reads.
+    unsafe fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            unsafe E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            unsafe fn (v: *const u8) => format!("*const u8: {}", *v),
+            unsafe fn (v: *const u16) => format!("*const u16: {}", v.read()),
+            fn (v: u8) => format!("u8: {}", v),
+            expr,
+        )
+    }
+
+    let value = 5u8;
+    unsafe {
+        assert_eq!(example(&value as *const u8), "*const u8: 5");
+        assert_eq!(example(&10u16 as *const u16), "*const u16: 10");
+        assert_eq!(example(5u8), "u8: 5");
+        assert_eq!(example(&value as *const u8 as *const i8), "default value");
+    }
+}
+
+#[test]
+fn test_unsafe_dispatch_variants() {
+    use specialized_dispatch::{dynamic_dispatch, specialized_match};
+    use std::any::Any;
+
+    unsafe fn matched<E>(expr: E) -> u8 {
+        specialized_match!(unsafe expr: E -> u8 {
+            unsafe v: *const u8 => *v,
+            _ => 0,
+        })
+    }
+
+    unsafe fn type_only<E>() -> &'static str {
+        specialized_dispatch!(
+            #[selected_arm]
+            unsafe E -> &'static str,
+            default fn <T> => "default",
+            unsafe fn <*const u8> => "*const u8",
+        )
+        .0
+    }
+
+    unsafe fn dynamic(value: &dyn Any) -> u8 {
+        dynamic_dispatch!(
+            unsafe &dyn Any -> u8,
+            unsafe fn (v: &*const u8) => **v,
+            default fn <T>(_: T) => 0,
+            value,
+        )
+    }
+
+    let value = 5u8;
+    let ptr = &value as *const u8;
+    unsafe {
+        assert_eq!(matched(ptr), 5);
+        assert_eq!(matched(value), 0);
+        assert_eq!(type_only::<*const u8>(), "*const u8");
+        assert_eq!(type_only::<u8>(), "default");
+        assert_eq!(dynamic(&ptr), 5);
+        assert_eq!(dynamic(&value), 0);
+    }
+}
+
+#[test]
+fn test_unsafe_function_pointer_type() {
+    fn example<E>(expr: E) -> &'static str {
+        specialized_dispatch!(
+            E -> &'static str,
+            default fn <T>(_: T) => "default",
+            fn (_: unsafe fn()) => "unsafe fn()",
+            expr,
+        )
+    }
+
+    unsafe fn noop() {}
+
+    assert_eq!(example(noop as unsafe fn()), "unsafe fn()");
+    assert_eq!(example(5u8), "default");
+}
+
+#[test]
+fn test_unsafe_block_input() {
+    fn example<E: Copy>(ptr: *const E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            fn (v: u8) => format!("u8: {}", v),
+            default fn <T>(_: T) => format!("default value"),
+            unsafe { *ptr },
+        )
+    }
+
+    assert_eq!(example(&1u8), "u8: 1");
+    assert_eq!(example(&1u16), "default value");
+}
+
+#[test]
+fn test_writer_extra_args() {
+    use std::fmt::{self, Debug};
+    use std::io::{self, Write as _};
+
+    fn write_io<E: Debug>(expr: E, out: &mut Vec<u8>) -> io::Result<()> {
+        specialized_dispatch!(
+            E -> io::Result<()>,
+            default fn <T: Debug>(v: T, out: &mut Vec<u8>) => write!(out, "{:?}", v),
+            fn (v: u8, out: &mut Vec<u8>) => out.write_all(&[v]),
+            expr,
+            out,
+        )
+    }
+
+    fn write_fmt<E: Debug>(expr: E, out: &mut dyn fmt::Write) -> fmt::Result {
+        specialized_dispatch!(
+            E -> fmt::Result,
+            default fn <T: Debug>(v: T, out: &mut dyn fmt::Write) => write!(out, "{:?}", v),
+            fn (v: char, out: &mut dyn fmt::Write) => out.write_char(v),
+            expr,
+            out,
+        )
+    }
+
+    struct Hex<E>(E);
+
+    impl<E: Debug> fmt::Display for Hex<E> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            specialized_dispatch!(
+                &E -> fmt::Result,
+                default fn <T: Debug>(v: T, f: &mut fmt::Formatter<'_>) => write!(f, "{:?}", v),
+                fn (v: &u8, f: &mut fmt::Formatter<'_>) => write!(f, "{:#x}", v),
+                &self.0,
+                f,
+            )
+        }
+    }
+
+    let mut out = Vec::new();
+    write_io(b'a', &mut out).unwrap();
+    write_io("b", &mut out).unwrap();
+    assert_eq!(out, b"a\"b\"");
+
+    let mut out = String::new();
+    write_fmt('a', &mut out).unwrap();
+    write_fmt("b", &mut out).unwrap();
+    assert_eq!(out, "a\"b\"");
+
+    assert_eq!(Hex(255u8).to_string(), "0xff");
+    assert_eq!(Hex(255u16).to_string(), "255");
+}
+
+#[test]
+fn test_borrowed_return_type() {
+    struct Context {
+        name: String,
+    }
+
+    // Lifetime is elided, since there is only one reference among arguments.
+    fn elided<E>(expr: E, ctx: &Context) -> &str {
+        specialized_dispatch!(
+            E -> &str,
+            default fn <T>(_: T, ctx: &Context) => &ctx.name,
+            fn (v: usize, ctx: &Context) => &ctx.name[..v],
+            expr,
+            ctx,
+        )
+    }
+
+    fn named<'a, E>(expr: E, out: &mut Vec<u8>, ctx: &'a Context) -> &'a str {
+        specialized_dispatch!(
+            E -> &'a str,
+            default fn <T>(_: T, out: &mut Vec<u8>, ctx: &'a Context) => {
+                out.push(0);
+                &ctx.name
+            },
+            fn (v: usize, out: &mut Vec<u8>, ctx: &'a Context) => {
+                out.push(v as u8);
+                &ctx.name[..v]
+            },
+            expr,
+            out,
+            ctx,
+        )
+    }
+
+    fn multiple<'a, 'b, E>(expr: E, first: &'a str, second: &'b str) -> (&'a str, &'b str) {
+        specialized_dispatch!(
+            E -> (&'a str, &'b str),
+            default fn <T>(_: T, first: &'a str, second: &'b str) => (first, second),
+            fn (_: bool, first: &'a str, second: &'b str) => (&first[1..], &second[1..]),
+            expr,
+            first,
+            second,
+        )
+    }
+
+    let ctx = Context {
+        name: "name".to_string(),
+    };
+    assert_eq!(elided(2usize, &ctx), "na");
+    assert_eq!(elided((), &ctx), "name");
+
+    let mut out = Vec::new();
+    let name = named(3usize, &mut out, &ctx);
+    out.clear();
+    assert_eq!(name, "nam");
+    assert_eq!(named((), &mut out, &ctx), "name");
+    assert_eq!(out, [0]);
+
+    assert_eq!(multiple(true, "ab", "cd"), ("b", "d"));
+    assert_eq!(multiple((), "ab", "cd"), ("ab", "cd"));
+}
+
+#[test]
+fn test_higher_ranked_lifetimes() {
+    // Lifetimes bound by `for<'a>` aren't introduced on the generated methods.
+    fn call<E>(expr: E, f: &dyn for<'a> Fn(&'a str) -> usize) -> usize {
+        specialized_dispatch!(
+            E -> usize,
+            default fn <T>(_: T, f: &dyn for<'a> Fn(&'a str) -> usize) => f(""),
+            fn (v: &str, f: &dyn for<'a> Fn(&'a str) -> usize) => f(v),
+            expr,
+            f,
+        )
+    }
+
+    fn trimmer<E>(expr: E) -> Box<dyn for<'a> Fn(&'a str) -> &'a str> {
+        specialized_dispatch!(
+            E -> Box<dyn for<'a> Fn(&'a str) -> &'a str>,
+            default fn <T>(_: T) => Box::new(|s| s),
+            fn (v: usize) => Box::new(move |s| &s[v..]),
+            expr,
+        )
+    }
+
+    assert_eq!(call("abc", &str::len), 3);
+    assert_eq!(call(5u8, &str::len), 0);
+    assert_eq!(trimmer(1usize)("abc"), "bc");
+    assert_eq!(trimmer(())("abc"), "abc");
+}
+
+#[test]
+fn test_borrowed_return_type_dynamic_dispatch() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+
+    struct Named {
+        name: String,
+    }
+
+    fn example<'a>(value: &'a dyn Any, fallback: &'a str, out: &mut Vec<u8>) -> &'a str {
+        dynamic_dispatch!(
+            &'a dyn Any -> &'a str,
+            fn (v: &'a Named, _fallback: &'a str, out: &mut Vec<u8>) => {
+                out.push(1);
+                &v.name
+            },
+            default fn <T>(_: T, fallback: &'a str, _out: &mut Vec<u8>) => fallback,
+            value,
+            fallback,
+            out,
+        )
+    }
+
+    let value = Named {
+        name: "value".to_string(),
+    };
+    let mut out = Vec::new();
+    assert_eq!(example(&value, "fallback", &mut out), "value");
+    assert_eq!(example(&5u8, "fallback", &mut out), "fallback");
+    assert_eq!(out, [1]);
+}
+
+#[test]
+fn test_generic_extra_args() {
+    use std::fmt::Debug;
+    use std::io::{self, Write};
+
+    fn serialize<E: Debug, W: Write>(expr: E, writer: &mut W) -> io::Result<()> {
+        specialized_dispatch!(
+            <W: Write> E -> io::Result<()>,
+            default fn <T: Debug>(v: T, writer: &mut W) => write!(writer, "{:?}", v),
+            fn (v: u8, writer: &mut W) => writer.write_all(&[v]),
+            expr,
+            writer,
+        )
+    }
+
+    let mut out = Vec::new();
+    serialize(b'a', &mut out).unwrap();
+    serialize("b", &mut out).unwrap();
+    assert_eq!(out, b"a\"b\"");
+}
+
+#[test]
+fn test_generic_extra_args_in_return_type() {
+    fn convert<E, O: Default + From<u8>, const N: usize>(expr: E) -> [O; N] {
+        specialized_dispatch!(
+            <O: Default + From<u8>, const N: usize> E -> [O; N],
+            default fn <T>(_: T) => std::array::from_fn(|_| O::default()),
+            fn (v: u8) => std::array::from_fn(|_| O::from(v)),
+            expr,
+        )
+    }
+
+    assert_eq!(convert::<_, u16, 2>(5u8), [5, 5]);
+    assert_eq!(convert::<_, u32, 3>("five"), [0, 0, 0]);
+}
+
+#[test]
+fn test_generic_extra_args_dynamic_dispatch() {
+    use specialized_dispatch::dynamic_dispatch;
+    use std::any::Any;
+    use std::fmt::Write;
+
+    fn describe<W: Write>(value: &dyn Any, writer: &mut W) -> std::fmt::Result {
+        dynamic_dispatch!(
+            <W: Write> &dyn Any -> std::fmt::Result,
+            fn (v: &u8, writer: &mut W) => write!(writer, "u8: {}", v),
+            default fn <T>(_: T, writer: &mut W) => writer.write_str("unknown"),
+            value,
+            writer,
+        )
+    }
+
+    let mut out = String::new();
+    describe(&5u8, &mut out).unwrap();
+    out.push_str(", ");
+    describe(&5u16, &mut out).unwrap();
+    assert_eq!(out, "u8: 5, unknown");
+}
+
+#[test]
+fn test_lifted_generics() {
+    trait Serializer {
+        type Error;
+
+        fn name(&self) -> &'static str;
+    }
+
+    trait Serialize<S: Serializer> {
+        fn serialize(&self, serializer: &S) -> Result<Vec<u8>, S::Error>;
+    }
+
+    struct Json;
+
+    impl Serializer for Json {
+        type Error = String;
+
+        fn name(&self) -> &'static str {
+            "json"
+        }
+    }
+
+    impl<S: Serializer> Serialize<S> for u8 {
+        fn serialize(&self, _: &S) -> Result<Vec<u8>, S::Error> {
+            Ok(vec![*self])
+        }
+    }
+
+    impl<S: Serializer> Serialize<S> for u16 {
+        fn serialize(&self, _: &S) -> Result<Vec<u8>, S::Error> {
+            Ok(self.to_le_bytes().to_vec())
+        }
+    }
+
+    fn serialize<E: Serialize<S>, S: Serializer>(
+        expr: E,
+        serializer: &S,
+    ) -> (Result<Vec<u8>, S::Error>, &'static str) {
+        specialized_dispatch!(
+            #[selected_arm]
+            #[lift(S: Serializer)]
+            E -> Result<Vec<u8>, S::Error>,
+            default fn <T: Serialize<S>>(v: T, serializer: &S) => v.serialize(serializer),
+            fn (v: u8, serializer: &S) => Ok(vec![v, serializer.name().len() as u8]),
+            expr,
+            serializer,
+        )
+    }
+
+    assert_eq!(serialize(5u8, &Json), (Ok(vec![5, 4]), "u8"));
+    assert_eq!(serialize(5u16, &Json), (Ok(vec![5, 0]), "T"));
+}
+
+#[test]
+fn test_lifted_generics_variants() {
+    use specialized_dispatch::{dynamic_dispatch, specialized_match};
+    use std::any::Any;
+
+    fn matched<E, O: Default + From<u8>>(expr: E) -> O {
+        specialized_match!(
+            #[lift(O: Default + From<u8>)]
+            expr: E -> O {
+                v: u8 => O::from(v),
+                _ => O::default(),
+            }
+        )
+    }
+
+    specialized_dispatch::closed_set!(trait Kind for u8, bool);
+
+    fn closed<E: Kind, O: From<u8> + From<bool>>(expr: E) -> O {
+        specialized_dispatch!(
+            #[closed(Kind)]
+            #[lift(O: From<u8> + From<bool>)]
+            E -> O,
+            fn (v: u8) => O::from(v),
+            fn (v: bool) => O::from(v),
+            expr,
+        )
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Borrowed<'a>(&'a u8);
+
+    impl<'a> From<&'a u8> for Borrowed<'a> {
+        fn from(v: &'a u8) -> Self {
+            Self(v)
+        }
+    }
+
+    fn dynamic<'a, O: From<&'a u8>>(value: &'a dyn Any) -> Option<O> {
+        dynamic_dispatch!(
+            #[lift('a, O: From<&'a u8>)]
+            &'a dyn Any -> Option<O>,
+            fn (v: &'a u8) => Some(O::from(v)),
+            default fn <T>(_: T) => None,
+            value,
+        )
+    }
+
+    assert_eq!(matched::<_, u16>(5u8), 5);
+    assert_eq!(matched::<_, u16>("five"), 0);
+    assert_eq!(closed::<_, u16>(5u8), 5);
+    assert_eq!(closed::<_, u16>(true), 1);
+    assert_eq!(dynamic::<Borrowed>(&5u8), Some(Borrowed(&5)));
+    assert_eq!(dynamic::<Borrowed>(&5u16), None);
+}
+
+#[test]
+fn test_try_arms() {
+    use std::num::ParseIntError;
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Parse(String),
+        Negative,
+    }
+
+    impl From<ParseIntError> for Error {
+        fn from(e: ParseIntError) -> Self {
+            Self::Parse(e.to_string())
+        }
+    }
+
+    fn parse<E>(expr: E) -> Result<u8, Error> {
+        specialized_dispatch!(
+            #[try_arms]
+            E -> Result<u8, Error>,
+            default fn <T>(_: T) => 0,
+            // Errors are converted into the declared error type.
+            fn (v: &str) => v.parse::<u8>()?,
+            fn (v: i32) => {
+                if v < 0 {
+                    Err(Error::Negative)?;
+                }
+                v as u8
+            },
+            expr,
+        )
+    }
+
+    assert_eq!(parse("5"), Ok(5));
+    assert!(matches!(parse("five"), Err(Error::Parse(_))));
+    assert_eq!(parse(5i32), Ok(5));
+    assert_eq!(parse(-5i32), Err(Error::Negative));
+    assert_eq!(parse(()), Ok(0));
+}
+
+#[test]
+fn test_try_arms_from_conversion() {
+    #[derive(Debug, PartialEq)]
+    struct TooLarge(u32);
+
+    #[derive(Debug, PartialEq)]
+    struct Empty;
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        TooLarge(u32),
+        Empty,
+    }
+
+    impl From<TooLarge> for Error {
+        fn from(e: TooLarge) -> Self {
+            Self::TooLarge(e.0)
+        }
+    }
+
+    impl From<Empty> for Error {
+        fn from(_: Empty) -> Self {
+            Self::Empty
+        }
+    }
+
+    fn narrow(v: u32) -> Result<u8, TooLarge> {
+        u8::try_from(v).map_err(|_| TooLarge(v))
+    }
+
+    fn first(v: &[u8]) -> Result<u8, Empty> {
+        v.first().copied().ok_or(Empty)
+    }
+
+    fn example<E>(expr: E) -> Result<u8, Error> {
+        specialized_dispatch!(
+            #[try_arms]
+            E -> Result<u8, Error>,
+            default fn <T>(_: T) => 0,
+            // Each arm has its own error type, converted with `From`.
+            fn (v: u32) => narrow(v)?,
+            fn (v: &[u8]) => first(v)?,
+            expr,
+        )
+    }
+
+    assert_eq!(example(5u32), Ok(5));
+    assert_eq!(example(500u32), Err(Error::TooLarge(500)));
+    assert_eq!(example(&[7u8][..]), Ok(7));
+    assert_eq!(example(&[0u8; 0][..]), Err(Error::Empty));
+    assert_eq!(example(()), Ok(0));
+}
+
+#[test]
+fn test_try_arms_variants() {
+    use specialized_dispatch::{dynamic_dispatch, specialized_match};
+    use std::any::Any;
+    use std::io;
+
+    fn matched<E>(expr: E) -> io::Result<String> {
+        specialized_match!(
+            #[try_arms]
+            #[selected_arm]
+            expr: E -> io::Result<String> {
+                v: &str => String::from_utf8(v.as_bytes().to_vec())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                _ => String::new(),
+            }
+        )
+        .0
+    }
+
+    fn dynamic(value: &dyn Any) -> Result<u8, std::num::TryFromIntError> {
+        dynamic_dispatch!(
+            #[try_arms]
+            &dyn Any -> Result<u8, std::num::TryFromIntError>,
+            fn (v: &u16) => u8::try_from(*v)?,
+            default fn <T>(_: T) => 0,
+            value,
+        )
+    }
+
+    assert_eq!(matched("five").unwrap(), "five");
+    assert_eq!(matched(5u8).unwrap(), "");
+    assert_eq!(dynamic(&5u16), Ok(5));
+    assert!(dynamic(&500u16).is_err());
+    assert_eq!(dynamic(&5u8), Ok(0));
+}
+
+#[test]
+fn test_block_bodies() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(_: T) => String::from("default value"),
+            fn (v: &[u8]) => {
+                let mut total = 0u32;
+                for x in v {
+                    total += u32::from(*x);
+                }
+                format!("sum: {}", total)
+            },
+            // `return` in closures and nested functions returns from them as usual.
+            fn (v: &str) => {
+                fn first_word(s: &str) -> &str {
+                    s.split(' ').next().unwrap_or_default()
+                }
+                let lengths: Vec<_> = v
+                    .split(',')
+                    .map(|s| {
+                        if s.is_empty() {
+                            return String::from("-");
+                        }
+                        first_word(s).len().to_string()
+                    })
+                    .collect();
+                lengths.join(",")
+            },
+            expr,
+        )
+    }
+
+    assert_eq!(example(&[1u8, 2, 3][..]), "sum: 6");
+    assert_eq!(example("ab cd,,efg"), "2,-,3");
+    assert_eq!(example(5u8), "default value");
+}
+
+#[test]
+fn test_control_flow() {
+    use std::ops::ControlFlow;
+
+    fn parse<E>(expr: E) -> Option<u8> {
+        let value = specialized_dispatch!(
+            #[control_flow]
+            E -> ControlFlow<Option<u8>, u8>,
+            default fn <T>(_: T) => ControlFlow::Break(None),
+            fn (v: &str) => match v.parse() {
+                Ok(v) => ControlFlow::Continue(v),
+                Err(_) => ControlFlow::Break(None),
+            },
+            fn (v: u8) => {
+                if v == u8::MAX {
+                    return ControlFlow::Break(Some(v));
+                }
+                ControlFlow::Continue(v)
+            },
+            expr,
+        );
+        // Only reached if the arm continues.
+        Some(value + 1)
+    }
+
+    assert_eq!(parse("5"), Some(6));
+    assert_eq!(parse("five"), None);
+    assert_eq!(parse(5u8), Some(6));
+    assert_eq!(parse(u8::MAX), Some(u8::MAX));
+    assert_eq!(parse(5u16), None);
+}
+
+#[test]
+fn test_control_flow_variants() {
+    use specialized_dispatch::{dynamic_dispatch, specialized_match};
+    use std::any::Any;
+    use std::ops::ControlFlow;
+
+    fn matched<E>(expr: E) -> &'static str {
+        let (value, arm) = specialized_match!(
+            #[control_flow]
+            #[selected_arm]
+            expr: E -> ControlFlow<&'static str, u8> {
+                v: u8 => ControlFlow::Continue(v),
+                _ => ControlFlow::Break("not u8"),
+            }
+        );
+        assert_eq!(arm, "u8");
+        if value == 0 {
+            "zero"
+        } else {
+            "non-zero"
+        }
+    }
+
+    fn dynamic(values: &[&dyn Any]) -> Result<u32, String> {
+        let mut total = 0;
+        for &value in values {
+            total += dynamic_dispatch!(
+                #[control_flow]
+                &dyn Any -> ControlFlow<Result<u32, String>, u32>,
+                fn (v: &u8) => ControlFlow::Continue(u32::from(*v)),
+                fn (v: &u16) => ControlFlow::Continue(u32::from(*v)),
+                default fn <T>(_: T) => ControlFlow::Break(Err(String::from("unexpected type"))),
+                value,
+            );
+        }
+        Ok(total)
+    }
+
+    assert_eq!(matched(0u8), "zero");
+    assert_eq!(matched(5u8), "non-zero");
+    assert_eq!(matched(5u16), "not u8");
+    assert_eq!(dynamic(&[&1u8, &2u16]), Ok(3));
+    assert_eq!(
+        dynamic(&[&1u8, &2u32]),
+        Err(String::from("unexpected type"))
+    );
+}
+
+#[test]
+fn test_specialize_impl() {
+    use specialized_dispatch::specialize_impl;
+
+    trait Describe {
+        const KIND: &'static str;
+
+        fn describe(&self) -> String;
+
+        fn name(&self) -> &'static str {
+            "unnamed"
+        }
+    }
+
+    specialize_impl! {
+        impl<T> Describe for T {
+            const KIND: &'static str = "any";
+
+            default fn describe(&self) -> String {
+                String::from("something")
+            }
+
+            default fn name(&self) -> &'static str {
+                "unnamed"
+            }
+        }
+
+        // `KIND` isn't specialized, so it's inherited from the implementation above.
+        impl<T> Describe for Vec<T> {
+            fn describe(&self) -> String {
+                format!("vector of length {}", self.len())
+            }
+
+            default fn name(&self) -> &'static str {
+                "vector"
+            }
+        }
+
+        // Specializes both of the implementations above.
+        impl Describe for Vec<u8> {
+            fn name(&self) -> &'static str {
+                "bytes"
+            }
+        }
+
+        impl Describe for u8 {
+            fn describe(&self) -> String {
+                format!("byte {}", self)
+            }
+
+            fn name(&self) -> &'static str {
+                "u8"
+            }
+        }
+    }
+
+    fn kind<T: Describe>(_: &T) -> &'static str {
+        T::KIND
+    }
+
+    assert_eq!(5u8.describe(), "byte 5");
+    assert_eq!(5u8.name(), "u8");
+    assert_eq!(kind(&5u8), "any");
+    assert_eq!(vec![1u16, 2].describe(), "vector of length 2");
+    assert_eq!(vec![1u16, 2].name(), "vector");
+    assert_eq!(vec![1u8, 2].describe(), "vector of length 2");
+    assert_eq!(vec![1u8, 2].name(), "bytes");
+    assert_eq!(1.5.describe(), "something");
+}
+
+#[test]
+fn test_specialize_impl_with_bounds() {
+    use specialized_dispatch::specialize_impl;
+
+    trait Named {
+        fn name(&self) -> &'static str;
+    }
+
+    impl Named for u8 {
+        fn name(&self) -> &'static str {
+            "u8"
+        }
+    }
+
+    trait Describe {
+        fn describe(&self) -> String;
+    }
+
+    specialize_impl! {
+        impl<T: Named, U> Describe for (T, U) {
+            default fn describe(&self) -> String {
+                format!("{} and something", self.0.name())
+            }
+        }
+
+        // Bounds implied by the implementation above are allowed.
+        impl<T: Named> Describe for (T, u16) {
+            fn describe(&self) -> String {
+                format!("{} and u16 {}", self.0.name(), self.1)
+            }
+        }
+
+        // Doesn't overlap with the implementation below, since `String` isn't `Named`.
+        impl<T: Named> Describe for T {
+            fn describe(&self) -> String {
+                String::from(self.name())
+            }
+        }
+
+        impl Describe for String {
+            fn describe(&self) -> String {
+                format!("string {}", self)
+            }
+        }
+    }
+
+    assert_eq!((5u8, 1.5).describe(), "u8 and something");
+    assert_eq!((5u8, 10u16).describe(), "u8 and u16 10");
+    assert_eq!(5u8.describe(), "u8");
+    assert_eq!(String::from("walnut").describe(), "string walnut");
+}
+
+#[test]
+fn test_bulk_dispatch() {
+    use std::fmt::Debug;
+
+    fn encode<E: Debug>(values: &[E], out: &mut Vec<u8>) {
+        specialized_dispatch!(
+            #[bulk]
+            &[E] -> (),
+            default fn <T: Debug>(v: &[T], out: &mut Vec<u8>) => {
+                for x in v {
+                    out.extend_from_slice(format!("{:?};", x).as_bytes());
+                }
+            },
+            fn (v: &[u8], out: &mut Vec<u8>) => out.extend_from_slice(v),
+            fn <const N: usize>(v: &[[u8; N]], out: &mut Vec<u8>) => {
+                out.extend(v.iter().flatten())
+            },
+            values,
+            out,
+        )
+    }
+
+    let mut out = Vec::new();
+    encode(&[1u8, 2], &mut out);
+    assert_eq!(out, [1, 2]);
+    out.clear();
+    encode(&Vec::from([[1u8, 2], [3, 4]]), &mut out);
+    assert_eq!(out, [1, 2, 3, 4]);
+    out.clear();
+    encode(&[1u16, 2], &mut out);
+    assert_eq!(out, b"1;2;");
+}
+
+#[test]
+fn test_bulk_dispatch_variants() {
+    use specialized_dispatch::specialized_match;
+
+    fn clear<E>(values: &mut [E]) -> (usize, &'static str) {
+        specialized_match!(
+            #[bulk]
+            #[selected_arm]
+            values: &mut [E] -> usize {
+                v: &mut [u8] => {
+                    v.fill(0);
+                    v.len()
+                }
+                _ => 0,
+            }
+        )
+    }
+
+    specialized_dispatch::closed_set!(trait Kind for u8, u16);
+
+    fn sum<E: Kind>(values: &[E]) -> u64 {
+        specialized_dispatch!(
+            #[bulk]
+            #[closed(Kind)]
+            &[E] -> u64,
+            fn (v: &[u8]) => v.iter().copied().map(u64::from).sum(),
+            fn (v: &[u16]) => v.iter().copied().map(u64::from).sum(),
+            values,
+        )
+    }
+
+    let mut values = vec![1u8, 2];
+    assert_eq!(clear(&mut values), (2, "u8"));
+    assert_eq!(values, [0, 0]);
+    assert_eq!(clear(&mut [1u16, 2]), (0, "T"));
+    assert_eq!(sum(&[1u8, 2]), 3);
+    assert_eq!(sum(&[1u16, 2, 3]), 6);
+}
+
+#[test]
+fn test_bulk_dispatch_vec() {
+    fn into_bytes<E: ToString>(values: Vec<E>) -> Vec<u8> {
+        specialized_dispatch!(
+            #[bulk]
+            Vec<E> -> Vec<u8>,
+            default fn <T: ToString>(v: Vec<T>) => {
+                v.into_iter().flat_map(|x| x.to_string().into_bytes()).collect()
+            },
+            // The vector is reused rather than copied.
+            fn (v: Vec<u8>) => v,
+            values,
+        )
+    }
+
+    let bytes = vec![1u8, 2];
+    let pointer = bytes.as_ptr();
+    let bytes = into_bytes(bytes);
+    assert_eq!(bytes, [1, 2]);
+    assert_eq!(bytes.as_ptr(), pointer);
+    assert_eq!(into_bytes(vec![1u16, 2]), b"12");
+}
@@ -0,0 +1,2 @@
+// The feature is enabled at the crate root rather than in this file.
+specialized_dispatch::check_setup!();
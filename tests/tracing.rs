@@ -0,0 +1,132 @@
+#![cfg(feature = "tracing")]
+#![feature(min_specialization)]
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use specialized_dispatch::{dynamic_dispatch, specialized_dispatch};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+/// Fields of a recorded span.
+#[derive(Debug, Default, PartialEq)]
+struct SpanFields {
+    location: String,
+    arm: String,
+    dispatched_type: String,
+}
+
+impl Visit for SpanFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "location" => self.location = value.to_string(),
+            "arm" => self.arm = value.to_string(),
+            "dispatched_type" => self.dispatched_type = value.to_string(),
+            name => panic!("unexpected field: {}", name),
+        }
+    }
+}
+
+/// Subscriber recording fields of new spans.
+#[derive(Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<SpanFields>>>,
+    next_id: AtomicU64,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+        assert_eq!(attrs.metadata().name(), "dispatch");
+        let mut fields = SpanFields::default();
+        attrs.record(&mut fields);
+        self.spans.lock().unwrap().push(fields);
+        span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+/// Runs the function while recording spans, and returns the recorded spans.
+fn record_spans(f: impl FnOnce()) -> Vec<SpanFields> {
+    let recorder = Recorder::default();
+    let spans = recorder.spans.clone();
+    tracing::subscriber::with_default(recorder, f);
+    let spans = std::mem::take(&mut *spans.lock().unwrap());
+    for span in &spans {
+        assert!(span.location.starts_with(file!()), "{}", span.location);
+    }
+    spans
+}
+
+/// Returns the recorded arms and dispatched types of the spans.
+fn arms(spans: &[SpanFields]) -> Vec<(&str, &str)> {
+    spans
+        .iter()
+        .map(|span| (span.arm.as_str(), span.dispatched_type.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_tracing_spans() {
+    fn example<E>(expr: E) -> String {
+        specialized_dispatch!(
+            E -> String,
+            default fn <T>(_: T) => format!("default value"),
+            fn (v: u8) => format!("u8: {}", v),
+            fn fast_path(v: &str) => format!("&str: {}", v),
+            expr,
+        )
+    }
+
+    let spans = record_spans(|| {
+        assert_eq!(example(1u8), "u8: 1");
+        assert_eq!(example("ok"), "&str: ok");
+        assert_eq!(example(1.5), "default value");
+    });
+    assert_eq!(
+        arms(&spans),
+        [("u8", "u8"), ("fast_path", "&str"), ("T", "f64")]
+    );
+    assert_eq!(spans[0].location, spans[2].location);
+}
+
+#[test]
+fn test_tracing_spans_dynamic_dispatch() {
+    fn example(value: &dyn std::any::Any) -> &'static str {
+        dynamic_dispatch!(
+            &dyn std::any::Any -> &'static str,
+            fn (_: &u8) => "u8",
+            default fn <T>(_: T) => "other",
+            value,
+        )
+    }
+
+    let spans = record_spans(|| {
+        assert_eq!(example(&1u8), "u8");
+        assert_eq!(example(&1u16), "other");
+    });
+    assert_eq!(arms(&spans), [("&u8", "&u8"), ("T", "&dyn core::any::Any")]);
+}
@@ -0,0 +1,14 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T) => format!("default value"),
+        fn (v u8) => format!("u8: {}", v),
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E, prefix: &str) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T, prefix: &str) => format!("{}default value", prefix),
+        fn (v: u8) => format!("u8: {}", v),
+        expr,
+        prefix,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(values: &mut [E]) -> usize {
+    specialized_dispatch!(
+        #[bulk]
+        &mut [E] -> usize,
+        default fn <T>(v: &mut [T]) => v.len(),
+        fn (v: &[u8]) => v.len(),
+        values,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(values: Box<[E]>) -> usize {
+    specialized_dispatch!(
+        #[bulk]
+        Box<[E]> -> usize,
+        default fn <T>(v: Box<[T]>) => v.len(),
+        fn (v: Box<[u8]>) => v.len(),
+        values,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E, prefix: &str) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T, prefix: &str) => format!("{}default value", prefix),
+        fn (v: u8, prefix: &str) => format!("{}u8: {}", prefix, v),
+        expr,
+        prefix,
+        "suffix",
+    )
+}
+
+fn main() {}
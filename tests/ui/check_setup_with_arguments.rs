@@ -0,0 +1,5 @@
+#![feature(min_specialization)]
+
+specialized_dispatch::check_setup!(min_specialization);
+
+fn main() {}
@@ -0,0 +1,14 @@
+use specialized_dispatch::specialized_dispatch;
+
+specialized_dispatch::check_setup!();
+
+fn example<E: Clone>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T: Clone>(_: T) => format!("default value"),
+        fn (v: u8) => format!("u8: {}", v),
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+use specialized_dispatch::closed_set;
+
+closed_set!(pub trait Kind for u8, u16, u8);
+
+fn main() {}
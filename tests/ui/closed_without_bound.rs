@@ -0,0 +1,18 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::{closed_set, specialized_dispatch};
+
+closed_set!(trait Kind for u8, u16);
+
+// The dispatched type must be bounded by the trait of the set.
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        #[closed(Kind)]
+        E -> String,
+        fn (v: u8) => format!("u8: {}", v),
+        fn (v: u16) => format!("u16: {}", v),
+        expr,
+    )
+}
+
+fn main() {}
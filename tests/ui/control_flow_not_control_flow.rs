@@ -0,0 +1,16 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> Option<u8> {
+    let value = specialized_dispatch!(
+        #[control_flow]
+        E -> Option<u8>,
+        default fn <T>(_: T) => None,
+        fn (v: u8) => Some(v),
+        expr,
+    );
+    value
+}
+
+fn main() {}
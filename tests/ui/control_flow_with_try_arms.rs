@@ -0,0 +1,17 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> Result<u8, ()> {
+    let value = specialized_dispatch!(
+        #[try_arms]
+        #[control_flow]
+        E -> std::ops::ControlFlow<Result<u8, ()>, u8>,
+        default fn <T>(_: T) => ControlFlow::Break(Err(())),
+        fn (v: u8) => ControlFlow::Continue(v),
+        expr,
+    );
+    Ok(value)
+}
+
+fn main() {}
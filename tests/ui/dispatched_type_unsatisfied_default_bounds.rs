@@ -0,0 +1,16 @@
+#![feature(min_specialization)]
+
+use std::fmt::Display;
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T: Display>(v: T) => format!("default value: {}", v),
+        fn (v: u8) => format!("u8: {}", v),
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use specialized_dispatch::dynamic_dispatch;
+use std::any::Any;
+
+fn example(value: &dyn Any) -> String {
+    dynamic_dispatch!(
+        &dyn Any -> String,
+        fn (v: u8) => format!("u8: {}", v),
+        default fn <T>(_: T) => format!("default value"),
+        value,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use std::any::Any;
+
+use specialized_dispatch::dynamic_dispatch;
+
+fn example(values: &[&dyn Any]) -> usize {
+    dynamic_dispatch!(
+        #[bulk]
+        &[&dyn Any] -> usize,
+        fn (v: &[&u8]) => v.len(),
+        default fn <T>(_: &[T]) => 0,
+        values,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use specialized_dispatch::dynamic_dispatch;
+use std::any::Any;
+
+fn example(_value: &dyn Any) -> &'static str {
+    dynamic_dispatch!(
+        &dyn Any -> &'static str,
+        default fn <T> => "default",
+        fn <u8> => "u8",
+    )
+}
+
+fn main() {}
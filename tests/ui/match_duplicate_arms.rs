@@ -0,0 +1,13 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_match;
+
+fn example<E>(expr: E) -> String {
+    specialized_match!(expr: E -> String {
+        v: &str => format!("str: {}", v),
+        v: &str => format!("str again: {}", v),
+        _ => format!("default value"),
+    })
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_match;
+
+fn example<E>(expr: E) -> String {
+    specialized_match!(expr: E -> String {
+        v: u8 => format!("u8: {}", v)
+        _ => format!("default value"),
+    })
+}
+
+fn main() {}
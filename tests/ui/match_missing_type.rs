@@ -0,0 +1,12 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_match;
+
+fn example<E>(expr: E) -> String {
+    specialized_match!(expr: E -> String {
+        <T> v => format!("default value"),
+        v: u8 => format!("u8: {}", v),
+    })
+}
+
+fn main() {}
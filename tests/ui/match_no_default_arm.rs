@@ -0,0 +1,11 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_match;
+
+fn example(expr: u8) -> String {
+    specialized_match!(expr: u8 -> String {
+        v: u8 => format!("u8: {}", v),
+    })
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E,
+        default fn <T>(_: T) => format!("default value"),
+        expr,
+    )
+}
+
+fn main() {}
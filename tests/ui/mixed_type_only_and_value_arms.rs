@@ -0,0 +1,14 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> &'static str {
+    specialized_dispatch!(
+        E -> &'static str,
+        default fn <T> => "default",
+        fn (_: u8) => "u8",
+        expr,
+    )
+}
+
+fn main() {}
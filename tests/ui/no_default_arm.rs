@@ -0,0 +1,13 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example(expr: u8) -> String {
+    specialized_dispatch!(
+        u8 -> String,
+        fn (v: u8) => format!("u8: {}", v),
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+#![feature(min_specialization)]
+#![deny(deprecated)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T>(_: T) => format!("default value"),
+        fn (v: u8) => {
+            if v == 0 {
+                return format!("zero");
+            }
+            format!("u8: {}", v)
+        },
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> (String, &'static str) {
+    specialized_dispatch!(
+        #[selected_arm(label)]
+        E -> String,
+        default fn <T>(_: T) => format!("default value"),
+        expr,
+    )
+}
+
+fn main() {}
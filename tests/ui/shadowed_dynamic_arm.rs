@@ -0,0 +1,18 @@
+#![deny(deprecated)]
+
+use specialized_dispatch::dynamic_dispatch;
+use std::any::Any;
+
+fn example(value: &dyn Any) -> String {
+    dynamic_dispatch!(
+        &dyn Any -> String,
+        fn (v: &u8) => format!("u8: {}", v),
+        fn (v: &core::primitive::u8) => format!("u8 again: {}", v),
+        fn (v: &String) => format!("String: {}", v),
+        fn (v: &::std::string::String) => format!("String again: {}", v),
+        default fn <T>(_: T) => format!("default value"),
+        value,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialize_impl;
+
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+specialize_impl! {
+    impl<T> Describe for T {
+        default fn describe(&self) -> String {
+            String::from("something")
+        }
+    }
+
+    // Bounds of specializing implementations are left to the compiler.
+    impl<T: std::fmt::Debug> Describe for Vec<T> {
+        fn describe(&self) -> String {
+            format!("{:?}", self)
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialize_impl;
+
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+trait Name {
+    fn name(&self) -> String;
+}
+
+specialize_impl! {
+    impl<T> Describe for T {
+        default fn describe(&self) -> String {
+            String::from("something")
+        }
+    }
+
+    impl Name for u8 {
+        fn name(&self) -> String {
+            String::from("u8")
+        }
+    }
+}
+
+fn main() {}
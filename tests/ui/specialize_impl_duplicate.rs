@@ -0,0 +1,23 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialize_impl;
+
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+specialize_impl! {
+    impl<T> Describe for Vec<T> {
+        default fn describe(&self) -> String {
+            String::from("vector")
+        }
+    }
+
+    impl<U> Describe for Vec<U> {
+        fn describe(&self) -> String {
+            String::from("vector again")
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialize_impl;
+
+struct Wrapper;
+
+specialize_impl! {
+    impl Wrapper {
+        fn describe(&self) -> String {
+            String::from("wrapper")
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialize_impl;
+
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+specialize_impl! {
+    impl<T> Describe for T {
+        fn describe(&self) -> String {
+            String::from("something")
+        }
+    }
+
+    impl Describe for u8 {
+        fn describe(&self) -> String {
+            format!("byte {}", self)
+        }
+    }
+}
+
+fn main() {}
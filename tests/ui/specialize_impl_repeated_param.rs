@@ -0,0 +1,23 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialize_impl;
+
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+specialize_impl! {
+    impl<T> Describe for T {
+        default fn describe(&self) -> String {
+            String::from("something")
+        }
+    }
+
+    impl<T> Describe for (T, T) {
+        fn describe(&self) -> String {
+            String::from("pair")
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> Result<u8, std::num::ParseIntError> {
+    specialized_dispatch!(
+        #[try_arms]
+        E -> Result<u8, _>,
+        default fn <T>(_: T) => 0,
+        fn (v: &str) => v.parse()?,
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> Option<u8> {
+    specialized_dispatch!(
+        #[try_arms]
+        E -> Option<u8>,
+        default fn <T>(_: T) => 0,
+        fn (v: &str) => v.parse().ok()?,
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+#![feature(min_specialization)]
+
+use specialized_dispatch::specialized_dispatch;
+
+fn example<E>(expr: E) -> u8 {
+    specialized_dispatch!(
+        unsafe E -> u8,
+        default fn <T>(_: T) => 0,
+        unsafe fn (v: *const u8) => *v,
+        expr,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+#![feature(min_specialization)]
+#![deny(deprecated)]
+
+use specialized_dispatch::specialized_dispatch;
+
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+impl Describe for u8 {
+    fn describe(&self) -> String {
+        format!("u8: {}", self)
+    }
+}
+
+struct Meters(f64);
+
+fn example<E: Describe>(expr: E) -> String {
+    specialized_dispatch!(
+        E -> String,
+        default fn <T: Describe>(v: T) => v.describe(),
+        fn (v: u8) => format!("specialized u8: {}", v),
+        fn (v: Meters) => format!("{} meters", v.0),
+        expr,
+    )
+}
+
+fn main() {}
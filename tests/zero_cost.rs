@@ -0,0 +1,92 @@
+// Compares the assembly of dispatches with hand-written code. Both backends of the crate are
+// covered: specialization for `specialized_dispatch` and `TypeId` checks for `dynamic_dispatch`
+// (there is no autoref-based backend). Functions are located by their labels in ELF assembly, so
+// the test only runs on x86-64 Linux.
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use std::path::Path;
+use std::process::Command;
+
+// Pairs of functions in `examples/zero_cost.rs`, a dispatch and its hand-written equivalent. The
+// last one is a dynamic dispatch, the others are specialized.
+const PAIRS: &[(&str, &str)] = &[
+    ("dispatch_u8", "manual_u8"),
+    ("dispatch_u16", "manual_u16"),
+    ("dispatch_default", "manual_default"),
+    ("dispatch_bulk", "manual_bulk"),
+    ("dynamic_u16", "manual_dynamic_u16"),
+];
+
+#[test]
+fn test_zero_cost() {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("zero-cost");
+    let status = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["rustc", "--quiet", "--release", "--example", "zero_cost"])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        // Identical functions would otherwise be merged, leaving nothing to compare.
+        .args(["--", "--emit=asm", "-Zmerge-functions=disabled"])
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "failed to build the `zero_cost` example");
+
+    let examples = target_dir.join("release").join("examples");
+    let asm = std::fs::read_dir(&examples)
+        .expect("failed to read the examples directory")
+        .map(|entry| entry.unwrap().path())
+        .find(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("zero_cost-") && name.ends_with(".s")
+        })
+        .expect("no assembly emitted for the `zero_cost` example");
+    let asm = std::fs::read_to_string(asm).unwrap();
+
+    for (dispatch, manual) in PAIRS {
+        assert_eq!(
+            function_body(&asm, dispatch),
+            function_body(&asm, manual),
+            "`{dispatch}` doesn't compile to the same code as `{manual}`",
+        );
+    }
+}
+
+// Returns the instructions and labels of a function, without directives. Local labels are
+// numbered after their function, so the number is dropped to compare functions.
+fn function_body(asm: &str, name: &str) -> Vec<String> {
+    let mut lines = asm.lines().skip_while(|line| *line != format!("{name}:"));
+    assert!(lines.next().is_some(), "`{name}` not found in the assembly");
+    lines
+        .take_while(|line| !line.trim().starts_with(".cfi_endproc"))
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !(line.starts_with('.') && !line.starts_with(".L")))
+        .filter(|line| !line.starts_with(".Lfunc_end"))
+        .map(normalize_labels)
+        .collect()
+}
+
+fn normalize_labels(line: &str) -> String {
+    let mut normalized = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = ["LBB", "LCPI", "LJTI"]
+        .iter()
+        .filter_map(|prefix| rest.find(prefix).map(|index| index + prefix.len()))
+        .min()
+    {
+        normalized.push_str(&rest[..start]);
+        rest = rest[start..].trim_start_matches(|c: char| c.is_ascii_digit());
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+#[test]
+fn test_normalize_labels() {
+    assert_eq!(normalize_labels("jne .LBB12_3"), "jne .LBB_3");
+    assert_eq!(normalize_labels(".LBB5_1:"), ".LBB_1:");
+    assert_eq!(
+        normalize_labels("movaps .LCPI4_0(%rip), %xmm0"),
+        "movaps .LCPI_0(%rip), %xmm0"
+    );
+    assert_eq!(normalize_labels("retq"), "retq");
+}